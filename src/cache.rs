@@ -0,0 +1,53 @@
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+pub const CACHE_DIR: &str = ".gh-export-cache";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedResponse {
+    pub url: String,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    pub link: Option<String>,
+    pub body: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpCache {
+    dir: PathBuf,
+}
+
+impl HttpCache {
+    pub fn new(output_dir: &Path) -> Self {
+        Self {
+            dir: output_dir.join(CACHE_DIR).join("http"),
+        }
+    }
+
+    pub fn lookup(&self, url: &str) -> Option<CachedResponse> {
+        let content = fs::read_to_string(self.entry_path(url)).ok()?;
+        let entry: CachedResponse = serde_json::from_str(&content).ok()?;
+        (entry.url == url).then_some(entry)
+    }
+
+    pub fn store(&self, entry: &CachedResponse) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+
+        let path = self.entry_path(&entry.url);
+        let partial_path = path.with_extension("json.part");
+        fs::write(&partial_path, serde_json::to_string(entry)?)?;
+        fs::rename(&partial_path, &path)?;
+
+        debug!("Cached response for {}", entry.url);
+        Ok(())
+    }
+
+    fn entry_path(&self, url: &str) -> PathBuf {
+        let key = format!("{:x}", Sha256::digest(url.as_bytes()));
+        self.dir.join(format!("{key}.json"))
+    }
+}
@@ -1,4 +1,6 @@
-use clap::{Parser, Subcommand};
+use crate::config::{Protocol, Visibility};
+use crate::filter::parse_size_kb;
+use clap::{Parser, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 #[derive(Parser)]
@@ -16,6 +18,43 @@ pub struct Cli {
     )]
     pub token: Option<String>,
 
+    #[arg(
+        long,
+        env = "GITHUB_API_URL",
+        help = "GitHub API base URL (e.g. https://github.example.com for Enterprise Server)"
+    )]
+    pub api_url: Option<String>,
+
+    #[arg(
+        long,
+        help = "Host used for clone URLs, if different from the API host"
+    )]
+    pub git_host: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        help = "Maximum time to wait for a rate limit to reset before giving up"
+    )]
+    pub max_rate_limit_wait: Option<u64>,
+
+    #[arg(long, help = "Do not use the on-disk HTTP cache for API responses")]
+    pub no_cache: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Protocol used to clone and fetch repositories"
+    )]
+    pub protocol: Option<Protocol>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Private key for SSH clones (defaults to ssh-agent)"
+    )]
+    pub ssh_key: Option<PathBuf>,
+
     #[arg(short, long, help = "Output directory for repositories")]
     pub output: Option<PathBuf>,
 
@@ -36,12 +75,125 @@ pub struct Cli {
     #[arg(long, help = "Perform shallow clones (depth=1)")]
     pub shallow: bool,
 
-    #[arg(short, long, help = "Filter repositories by name pattern")]
+    #[arg(
+        long,
+        help = "Create bare mirror clones with all refs, pruning deleted refs on update"
+    )]
+    pub mirror: bool,
+
+    #[arg(
+        long,
+        help = "Recursively initialize and update submodules, reusing local copies of exported repositories"
+    )]
+    pub recurse_submodules: bool,
+
+    #[arg(long, help = "Download Git LFS objects for every fetched ref")]
+    pub with_lfs: bool,
+
+    #[arg(
+        long,
+        help = "Export issues, pull requests, their comments and timelines as JSON"
+    )]
+    pub with_issues: bool,
+
+    #[arg(long, help = "Export releases and download their assets")]
+    pub with_releases: bool,
+
+    #[arg(long, help = "Back up repository wikis alongside each repository")]
+    pub with_wikis: bool,
+
+    #[arg(
+        long = "org",
+        value_name = "ORG",
+        help = "Also export repositories from this organization (repeatable)"
+    )]
+    pub orgs: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Also export repositories from every organization you belong to"
+    )]
+    pub all_orgs: bool,
+
+    #[arg(
+        short,
+        long,
+        value_name = "PATTERN",
+        help = "Only export repositories whose full name matches (glob, or regex with 're:' prefix; a plain word matches anywhere)"
+    )]
     pub filter: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        help = "Only export repositories whose full name matches (glob, or regex with 're:' prefix; repeatable)"
+    )]
+    pub include: Vec<String>,
+
+    #[arg(
+        long,
+        value_name = "PATTERN",
+        help = "Skip repositories whose full name matches (glob, or regex with 're:' prefix; repeatable)"
+    )]
+    pub exclude: Vec<String>,
+
+    #[arg(
+        long = "language",
+        value_name = "LANGUAGE",
+        help = "Only export repositories with this primary language (repeatable)"
+    )]
+    pub languages: Vec<String>,
+
+    #[arg(
+        long = "topic",
+        value_name = "TOPIC",
+        help = "Only export repositories tagged with this topic (repeatable)"
+    )]
+    pub topics: Vec<String>,
+
+    #[arg(long, value_enum, help = "Only export public or private repositories")]
+    pub visibility: Option<Visibility>,
+
+    #[arg(long, value_parser = parse_size_kb, help = "Skip repositories smaller than this size (e.g. 500KB, 10MB)")]
+    pub min_size: Option<u64>,
+
+    #[arg(long, value_parser = parse_size_kb, help = "Skip repositories larger than this size (e.g. 500KB, 10MB)")]
+    pub max_size: Option<u64>,
+
     #[arg(short, long, help = "Quiet mode - minimal output")]
     pub quiet: bool,
 
+    #[arg(
+        long,
+        global = true,
+        help = "Show what would be exported without cloning or changing anything on disk (export, sync and retry-failed only)"
+    )]
+    pub dry_run: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "Output format; json prints one JSON event per line"
+    )]
+    pub output_format: OutputFormat,
+
+    #[arg(
+        short = 'y',
+        long,
+        global = true,
+        help = "Answer yes to confirmation prompts"
+    )]
+    pub yes: bool,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Never prompt; fail with an error when input would be needed (implied without a terminal)"
+    )]
+    pub non_interactive: bool,
+
     #[arg(short, long, help = "Verbose logging")]
     pub verbose: bool,
 }
@@ -53,9 +205,16 @@ pub enum Commands {
         #[arg(
             short,
             long,
-            help = "Only update repositories modified after this date (YYYY-MM-DD)"
+            help = "Only update repositories modified after this date (YYYY-MM-DD). Defaults to the last export"
         )]
         since: Option<String>,
+
+        #[arg(
+            long,
+            conflicts_with = "since",
+            help = "Update every repository, ignoring the last export date"
+        )]
+        full: bool,
     },
 
     #[command(about = "Manage stored configuration")]
@@ -66,6 +225,24 @@ pub enum Commands {
 
     #[command(about = "Show information about the last export")]
     Status,
+
+    #[command(about = "List the repositories an export would include and what it would do")]
+    List {
+        #[arg(long, value_enum, default_value_t = ListFormat::Table, help = "Output format")]
+        format: ListFormat,
+    },
+
+    #[command(about = "Retry the repositories that failed in the last export")]
+    RetryFailed,
+
+    #[command(about = "Check that every backed-up repository is intact and up to date")]
+    Verify {
+        #[arg(
+            long,
+            help = "Only check repository integrity, without comparing against GitHub"
+        )]
+        offline: bool,
+    },
 }
 
 #[derive(Subcommand, Clone)]
@@ -75,7 +252,9 @@ pub enum ConfigAction {
 
     #[command(about = "Set a configuration value")]
     Set {
-        #[arg(help = "Configuration key (token, output, parallel)")]
+        #[arg(
+            help = "Configuration key (token, output, parallel, orgs, all_orgs, api_url, git_host)"
+        )]
         key: String,
 
         #[arg(help = "Value to set")]
@@ -85,3 +264,16 @@ pub enum ConfigAction {
     #[command(about = "Clear stored configuration")]
     Clear,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ListFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
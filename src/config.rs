@@ -1,4 +1,6 @@
 use crate::error::{GhExportError, Result};
+use crate::github::{normalize_api_base, DEFAULT_API_BASE};
+use clap::ValueEnum;
 use dirs::config_dir;
 use serde::{Deserialize, Serialize};
 use std::fs;
@@ -12,6 +14,80 @@ pub struct Config {
     pub include_archived: bool,
     pub exclude_forks: bool,
     pub shallow_clone: bool,
+    #[serde(default)]
+    pub mirror: bool,
+    #[serde(default)]
+    pub lfs: bool,
+    #[serde(default)]
+    pub recurse_submodules: bool,
+    #[serde(default)]
+    pub export_issues: bool,
+    #[serde(default)]
+    pub export_releases: bool,
+    #[serde(default)]
+    pub export_wikis: bool,
+    #[serde(default)]
+    pub organizations: Vec<String>,
+    #[serde(default)]
+    pub all_organizations: bool,
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    #[serde(default)]
+    pub languages: Vec<String>,
+    #[serde(default)]
+    pub topics: Vec<String>,
+    #[serde(default)]
+    pub visibility: Visibility,
+    #[serde(default)]
+    pub min_size_kb: Option<u64>,
+    #[serde(default)]
+    pub max_size_kb: Option<u64>,
+    #[serde(default)]
+    pub protocol: Protocol,
+    #[serde(default)]
+    pub ssh_key: Option<PathBuf>,
+    #[serde(default = "default_api_url")]
+    pub api_url: String,
+    #[serde(default)]
+    pub git_host: Option<String>,
+    #[serde(default = "default_max_rate_limit_wait")]
+    pub max_rate_limit_wait_secs: u64,
+    #[serde(default = "default_http_cache")]
+    pub http_cache: bool,
+    // `--filter` only applies to the current run.
+    #[serde(skip)]
+    pub name_filter: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    #[default]
+    All,
+    Public,
+    Private,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    #[default]
+    Https,
+    Ssh,
+}
+
+fn default_api_url() -> String {
+    DEFAULT_API_BASE.to_string()
+}
+
+fn default_http_cache() -> bool {
+    true
+}
+
+fn default_max_rate_limit_wait() -> u64 {
+    60 * 60
 }
 
 impl Default for Config {
@@ -23,6 +99,28 @@ impl Default for Config {
             include_archived: false,
             exclude_forks: false,
             shallow_clone: false,
+            mirror: false,
+            lfs: false,
+            recurse_submodules: false,
+            export_issues: false,
+            export_releases: false,
+            export_wikis: false,
+            organizations: Vec::new(),
+            all_organizations: false,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            languages: Vec::new(),
+            topics: Vec::new(),
+            visibility: Visibility::All,
+            min_size_kb: None,
+            max_size_kb: None,
+            protocol: Protocol::Https,
+            ssh_key: None,
+            api_url: default_api_url(),
+            git_host: None,
+            max_rate_limit_wait_secs: default_max_rate_limit_wait(),
+            http_cache: default_http_cache(),
+            name_filter: None,
         }
     }
 }
@@ -82,6 +180,23 @@ impl Config {
             ));
         }
 
+        if self.mirror && self.shallow_clone {
+            return Err(GhExportError::Config(
+                "Shallow clones cannot be combined with mirror mode".to_string(),
+            ));
+        }
+
+        if let Some(ssh_key) = &self.ssh_key {
+            if !ssh_key.is_file() {
+                return Err(GhExportError::Config(format!(
+                    "SSH key not found: {}",
+                    ssh_key.display()
+                )));
+            }
+        }
+
+        normalize_api_base(&self.api_url)?;
+
         Ok(())
     }
 
@@ -100,6 +215,8 @@ pub struct ExportMetadata {
     pub successful_exports: usize,
     pub failed_exports: Vec<String>,
     pub export_duration_seconds: u64,
+    #[serde(default)]
+    pub interrupted: bool,
 }
 
 impl ExportMetadata {
@@ -110,6 +227,31 @@ impl ExportMetadata {
         Ok(())
     }
 
+    // Exports save their metadata in the authenticated user's directory, so
+    // look there as well as at the top level.
+    pub fn find_latest(output_dir: &Path) -> Result<Option<Self>> {
+        let mut latest = Self::load(output_dir)?;
+
+        if output_dir.is_dir() {
+            for entry in fs::read_dir(output_dir)? {
+                let entry = entry?;
+                if !entry.file_type()?.is_dir() {
+                    continue;
+                }
+                if let Some(metadata) = Self::load(&entry.path())? {
+                    if latest
+                        .as_ref()
+                        .is_none_or(|latest| metadata.last_export > latest.last_export)
+                    {
+                        latest = Some(metadata);
+                    }
+                }
+            }
+        }
+
+        Ok(latest)
+    }
+
     pub fn load(output_dir: &Path) -> Result<Option<Self>> {
         let metadata_path = output_dir.join(".gh-export-metadata.json");
         if metadata_path.exists() {
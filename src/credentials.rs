@@ -0,0 +1,82 @@
+use crate::error::{GhExportError, Result};
+use base64::Engine;
+use git2::{Cred, CredentialType, RemoteCallbacks};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const OPENSSH_KEY_MAGIC: &[u8] = b"openssh-key-v1\0";
+
+#[derive(Clone)]
+pub struct GitCredentials {
+    pub token: String,
+    pub ssh_key: Option<PathBuf>,
+    pub ssh_passphrase: Option<String>,
+}
+
+impl GitCredentials {
+    pub fn remote_callbacks(&self) -> RemoteCallbacks<'_> {
+        let mut attempts = 0;
+        let mut callbacks = RemoteCallbacks::new();
+
+        callbacks.credentials(move |_url, username_from_url, allowed_types| {
+            attempts += 1;
+            if attempts > 3 {
+                return Err(git2::Error::new(
+                    git2::ErrorCode::Auth,
+                    git2::ErrorClass::Callback,
+                    "Authentication failed: credentials were rejected",
+                ));
+            }
+
+            let username = username_from_url.unwrap_or("git");
+
+            if allowed_types.contains(CredentialType::SSH_KEY) {
+                match &self.ssh_key {
+                    Some(key) => Cred::ssh_key(username, None, key, self.ssh_passphrase.as_deref()),
+                    None => Cred::ssh_key_from_agent(username),
+                }
+            } else if allowed_types.contains(CredentialType::USERNAME) {
+                Cred::username(username)
+            } else {
+                Cred::userpass_plaintext(username, &self.token)
+            }
+        });
+
+        callbacks
+    }
+}
+
+pub fn ssh_key_is_encrypted(path: &Path) -> Result<bool> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        GhExportError::Config(format!("Cannot read SSH key {}: {e}", path.display()))
+    })?;
+
+    if content.lines().any(|line| {
+        (line.starts_with("-----") || line.starts_with("Proc-Type:")) && line.contains("ENCRYPTED")
+    }) {
+        return Ok(true);
+    }
+
+    if !content.contains("BEGIN OPENSSH PRIVATE KEY") {
+        return Ok(false);
+    }
+
+    let encoded: String = content
+        .lines()
+        .filter(|line| !line.starts_with("-----"))
+        .collect();
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| GhExportError::Config(format!("Invalid SSH key {}: {e}", path.display())))?;
+
+    let Some(rest) = decoded.strip_prefix(OPENSSH_KEY_MAGIC) else {
+        return Ok(false);
+    };
+    let Some(length) = rest.get(..4) else {
+        return Ok(false);
+    };
+    let length = u32::from_be_bytes([length[0], length[1], length[2], length[3]]) as usize;
+    let cipher = rest.get(4..4 + length).unwrap_or_default();
+
+    Ok(cipher != b"none")
+}
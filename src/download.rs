@@ -1,59 +1,171 @@
-use crate::error::{GhExportError, Result};
-use crate::github::Repository;
+use crate::config::Protocol;
+use crate::credentials::GitCredentials;
+use crate::error::{http_status, GhExportError, Result};
+use crate::github::{GitHubClient, Repository};
+use crate::issues;
+use crate::journal::Journal;
+use crate::lfs::LfsClient;
 use crate::progress::ProgressTracker;
+use crate::releases;
+use crate::shutdown::Shutdown;
 use futures::StreamExt;
-use git2::{Cred, FetchOptions, RemoteCallbacks};
+use git2::{BranchType, FetchOptions};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::Semaphore;
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+
+const EXPORT_DATA_DIR: &str = ".gh-export";
+const MIRROR_REFSPEC: &str = "+refs/*:refs/*";
+// Clones are staged under a dot-directory of the output directory, where no
+// `<owner>/<name>` of a real repository can end up.
+const STAGING_DIR: &str = ".gh-export-staging";
+const MAX_ATTEMPTS: u32 = 3;
+const RETRY_DELAY: Duration = Duration::from_secs(2);
 
 pub struct Downloader {
     output_dir: PathBuf,
-    token: String,
-    shallow: bool,
+    credentials: GitCredentials,
+    options: DownloadOptions,
+    client: GitHubClient,
+    lfs: Option<LfsClient>,
+    journal: Arc<Journal>,
     progress: Arc<ProgressTracker>,
+    shutdown: Arc<Shutdown>,
+}
+
+#[derive(Debug, Default)]
+pub struct DownloadReport {
+    pub wiki: Option<WikiStatus>,
+    pub diverged_branches: Vec<String>,
+    pub lfs_objects: usize,
+    pub refs: BTreeMap<String, String>,
+    pub size_bytes: u64,
+}
+
+#[derive(Debug)]
+pub struct DownloadFailure {
+    pub step: &'static str,
+    pub error: GhExportError,
+    pub attempts: u32,
+}
+
+impl DownloadFailure {
+    fn during(step: &'static str) -> impl Fn(GhExportError) -> Self {
+        move |error| Self {
+            step,
+            error: error.classify(),
+            attempts: 1,
+        }
+    }
+}
+
+impl fmt::Display for DownloadFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} failed: {}", self.step, self.error)
+    }
+}
+
+// Repositories taking part in this export, used to point submodules at local
+// copies. Only URLs on one of `hosts` can refer to them.
+struct LocalRepositories {
+    paths: HashMap<String, PathBuf>,
+    hosts: HashSet<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WikiStatus {
+    Backed,
+    NotInitialized,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct DownloadOptions {
+    pub shallow: bool,
+    pub with_issues: bool,
+    pub with_releases: bool,
+    pub with_wikis: bool,
+    pub mirror: bool,
+    pub protocol: Protocol,
+    pub with_lfs: bool,
+    pub recurse_submodules: bool,
 }
 
 #[derive(Debug)]
-#[allow(dead_code)]
 pub enum DownloadResult {
-    Success,
+    Success(DownloadReport),
     Skipped(String),
-    Failed(String),
+    Cancelled,
+    Failed(DownloadFailure),
 }
 
 impl Downloader {
     pub fn new(
         output_dir: PathBuf,
-        token: String,
-        shallow: bool,
+        credentials: GitCredentials,
+        options: DownloadOptions,
+        client: GitHubClient,
+        journal: Arc<Journal>,
         progress: Arc<ProgressTracker>,
+        shutdown: Arc<Shutdown>,
     ) -> Self {
+        let lfs = options.with_lfs.then(|| LfsClient::new(client.clone()));
+
         Self {
             output_dir,
-            token,
-            shallow,
+            credentials,
+            options,
+            client,
+            lfs,
+            journal,
             progress,
+            shutdown,
         }
     }
 
     pub async fn download_repositories(
         &self,
         repositories: Vec<Repository>,
+        refresh_only: &HashSet<u64>,
         max_concurrent: usize,
     ) -> Result<Vec<(String, DownloadResult)>> {
         let semaphore = Arc::new(Semaphore::new(max_concurrent));
         let mut tasks = Vec::new();
 
+        let local_repos = Arc::new(LocalRepositories {
+            paths: repositories
+                .iter()
+                .map(|repo| (repo.full_name.to_lowercase(), self.repository_path(repo)))
+                .collect(),
+            hosts: repositories
+                .iter()
+                .flat_map(|repo| [&repo.clone_url, &repo.ssh_url])
+                .filter_map(|url| parse_remote_url(url).map(|(host, _)| host))
+                .collect(),
+        });
+
         for repo in repositories {
             let semaphore = semaphore.clone();
             let downloader = self.clone_for_task();
+            let local_repos = local_repos.clone();
+            let refresh_only = refresh_only.contains(&repo.id);
 
             let task = tokio::spawn(async move {
                 let _permit = semaphore.acquire().await.unwrap();
-                let result = downloader.download_repository(&repo).await;
+                let result = if downloader.shutdown.is_requested() {
+                    DownloadResult::Cancelled
+                } else {
+                    downloader.progress.repo_started(&repo.full_name);
+                    tokio::select! {
+                        result = downloader.download_repository(&repo, &local_repos, refresh_only) => result,
+                        () = downloader.shutdown.aborted() => DownloadResult::Cancelled,
+                    }
+                };
+                downloader.progress.repo_finished(&repo.full_name, &result);
                 (repo.full_name.clone(), result)
             });
 
@@ -75,61 +187,222 @@ impl Downloader {
         Ok(results)
     }
 
-    async fn download_repository(&self, repo: &Repository) -> DownloadResult {
-        let repo_path = self.output_dir.join(&repo.owner.login).join(&repo.name);
-
-        if repo_path.exists() {
-            debug!("Repository {} already exists, updating...", repo.full_name);
-            match self.update_repository(&repo_path).await {
-                Ok(_) => {
-                    self.progress.increment_completed();
-                    DownloadResult::Success
+    async fn download_repository(
+        &self,
+        repo: &Repository,
+        local_repos: &Arc<LocalRepositories>,
+        refresh_only: bool,
+    ) -> DownloadResult {
+        let mut attempt = 1;
+
+        let result = loop {
+            let mut report = DownloadReport::default();
+            match self
+                .try_download_repository(repo, local_repos, refresh_only, &mut report)
+                .await
+            {
+                Ok(()) => break Ok(report),
+                Err(failure)
+                    if failure.error.is_retryable()
+                        && attempt < MAX_ATTEMPTS
+                        && !self.shutdown.is_requested() =>
+                {
+                    let delay = RETRY_DELAY * 2u32.pow(attempt - 1);
+                    warn!(
+                        "{} for {} ({}), retrying in {}s",
+                        failure,
+                        repo.full_name,
+                        failure.error.category(),
+                        delay.as_secs()
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
                 }
-                Err(e) => {
-                    self.progress.increment_failed();
-                    DownloadResult::Failed(format!("Update failed: {e}"))
+                Err(mut failure) => {
+                    failure.attempts = attempt;
+                    break Err(failure);
                 }
             }
-        } else {
-            info!("Cloning repository {}", repo.full_name);
-            match self.clone_repository(repo, &repo_path).await {
-                Ok(_) => {
-                    self.progress.increment_completed();
-                    DownloadResult::Success
+        };
+
+        match result {
+            Ok(mut report) => {
+                let path = self.repository_path(repo);
+                let state = tokio::task::spawn_blocking(move || local_state(&path))
+                    .await
+                    .map_err(|e| GhExportError::Download(format!("Inspect task failed: {e}")))
+                    .and_then(|state| state);
+                match state {
+                    Ok((refs, size_bytes)) => {
+                        report.refs = refs;
+                        report.size_bytes = size_bytes;
+                    }
+                    Err(e) => warn!("Failed to inspect {}: {}", repo.full_name, e),
                 }
-                Err(e) => {
-                    self.progress.increment_failed();
-                    DownloadResult::Failed(format!("Clone failed: {e}"))
+
+                if let Err(e) = self.journal.record_completed(&repo.full_name) {
+                    warn!("Failed to record {} in the journal: {}", repo.full_name, e);
                 }
+                self.progress.increment_completed();
+                DownloadResult::Success(report)
+            }
+            // Transfers stopped by the abort callback surface as git errors.
+            Err(_) if self.shutdown.is_aborted() => DownloadResult::Cancelled,
+            Err(failure) => {
+                self.progress.increment_failed();
+                DownloadResult::Failed(failure)
+            }
+        }
+    }
+
+    async fn try_download_repository(
+        &self,
+        repo: &Repository,
+        local_repos: &Arc<LocalRepositories>,
+        refresh_only: bool,
+        report: &mut DownloadReport,
+    ) -> std::result::Result<(), DownloadFailure> {
+        let repo_path = self.repository_path(repo);
+
+        if refresh_only {
+            debug!(
+                "Repository {} is unchanged, only exporting issues and releases",
+                repo.full_name
+            );
+        } else {
+            self.fetch_git_data(repo, &repo_path, local_repos, report)
+                .await?;
+        }
+
+        if self.options.with_issues {
+            issues::export_issues(&self.client, repo, &repo_path)
+                .await
+                .map_err(DownloadFailure::during("Issue export"))?;
+        }
+
+        if self.options.with_releases {
+            releases::export_releases(&self.client, repo, &repo_path)
+                .await
+                .map_err(DownloadFailure::during("Release export"))?;
+        }
+
+        Ok(())
+    }
+
+    async fn fetch_git_data(
+        &self,
+        repo: &Repository,
+        repo_path: &Path,
+        local_repos: &Arc<LocalRepositories>,
+        report: &mut DownloadReport,
+    ) -> std::result::Result<(), DownloadFailure> {
+        if repo_path.exists() {
+            debug!("Repository {} already exists, updating...", repo.full_name);
+            report.diverged_branches = self
+                .update_repository(self.remote_url(repo), repo_path, Some(&repo.default_branch))
+                .await
+                .map_err(DownloadFailure::during("Update"))?;
+        } else {
+            info!("Cloning repository {}", repo.full_name);
+            self.clone_repository(self.remote_url(repo), &repo.full_name, repo_path)
+                .await
+                .map_err(DownloadFailure::during("Clone"))?;
+        }
+
+        if self.options.recurse_submodules && !self.options.mirror {
+            self.update_submodules(repo, repo_path, local_repos.clone())
+                .await
+                .map_err(DownloadFailure::during("Submodule update"))?;
+        }
+
+        if let Some(lfs) = &self.lfs {
+            report.lfs_objects = lfs
+                .fetch_objects(&repo.clone_url, repo_path, self.options.mirror)
+                .await
+                .map_err(DownloadFailure::during("LFS download"))?;
+        }
+
+        if self.options.with_wikis && repo.has_wiki {
+            report.wiki = Some(
+                self.backup_wiki(repo)
+                    .await
+                    .map_err(DownloadFailure::during("Wiki backup"))?,
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn backup_wiki(&self, repo: &Repository) -> Result<WikiStatus> {
+        let wiki_path = self
+            .output_dir
+            .join(&repo.owner.login)
+            .join(format!("{}.wiki", repo.name));
+        let remote_url = self.remote_url(repo);
+        let wiki_url = format!(
+            "{}.wiki.git",
+            remote_url.strip_suffix(".git").unwrap_or(remote_url)
+        );
+
+        let result = if wiki_path.exists() {
+            debug!("Updating wiki for {}", repo.full_name);
+            self.update_repository(&wiki_url, &wiki_path, None)
+                .await
+                .map(|_| ())
+        } else {
+            info!("Cloning wiki for {}", repo.full_name);
+            self.clone_repository(&wiki_url, &repo.full_name, &wiki_path)
+                .await
+        };
+
+        match result {
+            Ok(()) => Ok(WikiStatus::Backed),
+            Err(GhExportError::Git(e)) if is_missing_remote(&e) => {
+                debug!("Repository {} has no wiki content", repo.full_name);
+                Ok(WikiStatus::NotInitialized)
             }
+            Err(e) => Err(e),
         }
     }
 
-    async fn clone_repository(&self, repo: &Repository, target_path: &Path) -> Result<()> {
+    async fn clone_repository(
+        &self,
+        clone_url: &str,
+        // Progress is reported under the repository's full name, wikis included,
+        // so that it lines up with the other events for the repository.
+        full_name: &str,
+        target_path: &Path,
+    ) -> Result<()> {
         if let Some(parent) = target_path.parent() {
             fs::create_dir_all(parent)?;
         }
 
-        let token = self.token.clone();
-        let clone_url = repo.clone_url.clone();
+        let staging_path = self.staging_path(target_path)?;
+        if let Some(parent) = staging_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if staging_path.exists() {
+            fs::remove_dir_all(&staging_path)?;
+        }
+
+        let credentials = self.credentials.clone();
+        let clone_url = clone_url.to_string();
         let target_path = target_path.to_path_buf();
-        let shallow = self.shallow;
+        let shallow = self.options.shallow;
+        let mirror = self.options.mirror;
         let progress = self.progress.clone();
-        let repo_name = repo.name.clone();
+        let shutdown = self.shutdown.clone();
+        let full_name = full_name.to_string();
 
         tokio::task::spawn_blocking(move || {
-            let mut callbacks = RemoteCallbacks::new();
-            callbacks.credentials(|_url, username_from_url, _allowed_types| {
-                Cred::userpass_plaintext(username_from_url.unwrap_or("git"), &token)
-            });
-
+            let mut callbacks = credentials.remote_callbacks();
             callbacks.transfer_progress(|stats| {
                 let received = stats.received_objects();
                 let total = stats.total_objects();
                 if total > 0 {
-                    progress.update_repo_progress(&repo_name, received as u32, total as u32);
+                    progress.update_repo_progress(&full_name, received as u32, total as u32);
                 }
-                true
+                !shutdown.is_aborted()
             });
 
             let mut fetch_options = FetchOptions::new();
@@ -142,78 +415,467 @@ impl Downloader {
             let mut builder = git2::build::RepoBuilder::new();
             builder.fetch_options(fetch_options);
 
-            builder
-                .clone(&clone_url, &target_path)
-                .map_err(GhExportError::Git)?;
+            if mirror {
+                builder.bare(true);
+                builder.remote_create(|repo, name, url| {
+                    let remote = repo.remote_with_fetch(name, url, MIRROR_REFSPEC)?;
+                    repo.config()?
+                        .set_bool(&format!("remote.{name}.mirror"), true)?;
+                    Ok(remote)
+                });
+            }
+
+            let result = match builder.clone(&clone_url, &staging_path) {
+                Ok(_) => fs::rename(&staging_path, &target_path).map_err(GhExportError::from),
+                Err(e) => {
+                    let _ = fs::remove_dir_all(&staging_path);
+                    Err(GhExportError::Git(e))
+                }
+            };
+
+            // Drop the owner directory and the staging root once they are empty;
+            // other clones may still be using them.
+            for dir in staging_path.ancestors().skip(1).take(2) {
+                if fs::remove_dir(dir).is_err() {
+                    break;
+                }
+            }
 
-            Ok(())
+            result
         })
         .await
         .map_err(|e| GhExportError::Download(format!("Clone task failed: {e}")))?
     }
 
-    async fn update_repository(&self, repo_path: &Path) -> Result<()> {
-        let token = self.token.clone();
+    async fn update_repository(
+        &self,
+        remote_url: &str,
+        repo_path: &Path,
+        default_branch: Option<&str>,
+    ) -> Result<Vec<String>> {
+        let credentials = self.credentials.clone();
+        let shutdown = self.shutdown.clone();
+        let remote_url = remote_url.to_string();
         let repo_path = repo_path.to_path_buf();
+        let default_branch = default_branch.map(str::to_string);
 
         tokio::task::spawn_blocking(move || {
             let repo = git2::Repository::open(&repo_path)?;
+            // Follow changes of `--protocol` (and of the upstream location).
+            if repo.find_remote("origin")?.url() != Some(remote_url.as_str()) {
+                repo.remote_set_url("origin", &remote_url)?;
+            }
             let mut remote = repo.find_remote("origin")?;
 
-            let mut callbacks = RemoteCallbacks::new();
-            callbacks.credentials(|_url, username_from_url, _allowed_types| {
-                Cred::userpass_plaintext(username_from_url.unwrap_or("git"), &token)
-            });
+            let mut callbacks = credentials.remote_callbacks();
+            callbacks.transfer_progress(|_| !shutdown.is_aborted());
 
             let mut fetch_options = FetchOptions::new();
             fetch_options.remote_callbacks(callbacks);
+            fetch_options.prune(git2::FetchPrune::On);
+
+            if repo.is_bare() {
+                remote.fetch(&[MIRROR_REFSPEC], Some(&mut fetch_options), None)?;
+
+                // Pruning removes a renamed default branch, so HEAD has to follow
+                // the new one.
+                if let Some(branch) = default_branch {
+                    let head = format!("refs/heads/{branch}");
+                    if repo.find_reference(&head).is_ok() {
+                        repo.set_head(&head)?;
+                    }
+                }
+                return Ok(Vec::new());
+            }
 
             remote.fetch(
-                &["refs/heads/*:refs/remotes/origin/*"],
+                &["+refs/heads/*:refs/remotes/origin/*"],
                 Some(&mut fetch_options),
                 None,
             )?;
 
-            let fetch_head = repo.find_reference("FETCH_HEAD")?;
-            let fetch_commit = repo.reference_to_annotated_commit(&fetch_head)?;
-
-            let analysis = repo.merge_analysis(&[&fetch_commit])?;
+            sync_local_branches(&repo, default_branch.as_deref())
+        })
+        .await
+        .map_err(|e| GhExportError::Download(format!("Update task failed: {e}")))?
+    }
 
-            if analysis.0.is_fast_forward() {
-                let refname = "refs/heads/master";
-                match repo.find_reference(refname) {
-                    Ok(mut reference) => {
-                        reference.set_target(fetch_commit.id(), "Fast-forward")?;
-                        repo.set_head(refname)?;
-                        repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
-                    }
-                    Err(_) => {
-                        let refname = "refs/heads/main";
-                        if let Ok(mut reference) = repo.find_reference(refname) {
-                            reference.set_target(fetch_commit.id(), "Fast-forward")?;
-                            repo.set_head(refname)?;
-                            repo.checkout_head(Some(
-                                git2::build::CheckoutBuilder::default().force(),
-                            ))?;
-                        }
-                    }
-                }
-            }
+    async fn update_submodules(
+        &self,
+        repo: &Repository,
+        repo_path: &Path,
+        local_repos: Arc<LocalRepositories>,
+    ) -> Result<()> {
+        let credentials = self.credentials.clone();
+        let repo_path = repo_path.to_path_buf();
+        let full_name = repo.full_name.clone();
 
-            Ok(())
+        tokio::task::spawn_blocking(move || {
+            let repo = git2::Repository::open(&repo_path)?;
+            update_submodules_recursive(&repo, Some(&full_name), &credentials, &local_repos)
         })
         .await
-        .map_err(|e| GhExportError::Download(format!("Update task failed: {e}")))?
+        .map_err(|e| GhExportError::Download(format!("Submodule task failed: {e}")))?
+    }
+
+    fn staging_path(&self, target_path: &Path) -> Result<PathBuf> {
+        let relative = target_path.strip_prefix(&self.output_dir).map_err(|_| {
+            GhExportError::Download(format!(
+                "{} is outside the output directory",
+                target_path.display()
+            ))
+        })?;
+        Ok(self.output_dir.join(STAGING_DIR).join(relative))
+    }
+
+    fn repository_path(&self, repo: &Repository) -> PathBuf {
+        self.output_dir.join(&repo.owner.login).join(&repo.name)
+    }
+
+    fn remote_url<'a>(&self, repo: &'a Repository) -> &'a str {
+        match self.options.protocol {
+            Protocol::Https => &repo.clone_url,
+            Protocol::Ssh => &repo.ssh_url,
+        }
     }
 
     fn clone_for_task(&self) -> Self {
         Self {
             output_dir: self.output_dir.clone(),
-            token: self.token.clone(),
-            shallow: self.shallow,
+            credentials: self.credentials.clone(),
+            options: self.options.clone(),
+            client: self.client.clone(),
+            lfs: self.lfs.clone(),
+            journal: self.journal.clone(),
             progress: self.progress.clone(),
+            shutdown: self.shutdown.clone(),
+        }
+    }
+}
+
+fn sync_local_branches(
+    repo: &git2::Repository,
+    default_branch: Option<&str>,
+) -> Result<Vec<String>> {
+    let head_branch = repo
+        .head()
+        .ok()
+        .filter(|head| head.is_branch())
+        .and_then(|head| head.shorthand().map(str::to_string));
+
+    // A forced checkout would throw away uncommitted work, so the checked-out
+    // branch is left alone while there is any.
+    let dirty = has_local_changes(repo)?;
+    let mut diverged = Vec::new();
+    let mut head_moved = false;
+
+    for branch in repo.branches(Some(BranchType::Remote))? {
+        let (remote_branch, _) = branch?;
+        let Some(remote_name) = remote_branch.name()?.map(str::to_string) else {
+            continue;
+        };
+        let Some(name) = remote_name.strip_prefix("origin/") else {
+            continue;
+        };
+        if name == "HEAD" {
+            continue;
+        }
+        let Some(remote_oid) = remote_branch.get().target() else {
+            continue;
+        };
+
+        match repo.find_branch(name, BranchType::Local) {
+            Ok(mut local_branch) => {
+                let Some(local_oid) = local_branch.get().target() else {
+                    continue;
+                };
+
+                if local_oid == remote_oid {
+                    continue;
+                }
+
+                let is_head = head_branch.as_deref() == Some(name);
+                if repo.graph_descendant_of(remote_oid, local_oid)? {
+                    if is_head && dirty {
+                        warn!(
+                            "Branch {} has uncommitted changes, not fast-forwarding it to {}",
+                            name, remote_name
+                        );
+                        continue;
+                    }
+                    local_branch
+                        .get_mut()
+                        .set_target(remote_oid, "Fast-forward")?;
+                    head_moved |= is_head;
+                } else {
+                    warn!(
+                        "Branch {} cannot be fast-forwarded to {}",
+                        name, remote_name
+                    );
+                    diverged.push(name.to_string());
+                }
+            }
+            Err(_) => {
+                let commit = repo.find_commit(remote_oid)?;
+                let mut local_branch = repo.branch(name, &commit, false)?;
+                local_branch.set_upstream(Some(&remote_name))?;
+            }
+        }
+    }
+
+    let target_head = default_branch
+        .filter(|branch| repo.find_branch(branch, BranchType::Local).is_ok())
+        .map(str::to_string)
+        .or(head_branch.clone());
+
+    if let Some(target_head) =
+        target_head.filter(|target| head_branch.as_deref() != Some(target.as_str()))
+    {
+        if dirty {
+            warn!(
+                "Uncommitted changes on {}, not switching to {}",
+                head_branch.as_deref().unwrap_or("HEAD"),
+                target_head
+            );
+        } else {
+            repo.set_head(&format!("refs/heads/{target_head}"))?;
+            head_moved = true;
+        }
+    }
+
+    if head_moved {
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))?;
+    }
+
+    Ok(diverged)
+}
+
+fn has_local_changes(repo: &git2::Repository) -> Result<bool> {
+    let mut options = git2::StatusOptions::new();
+    options.include_untracked(true).include_ignored(false);
+    Ok(!repo.statuses(Some(&mut options))?.is_empty())
+}
+
+fn local_state(repo_path: &Path) -> Result<(BTreeMap<String, String>, u64)> {
+    let repo = git2::Repository::open(repo_path)?;
+    let mut refs = BTreeMap::new();
+
+    for reference in repo.references()? {
+        let reference = reference?;
+        if let (Some(name), Some(target)) = (reference.name(), reference.target()) {
+            refs.insert(name.to_string(), target.to_string());
+        }
+    }
+
+    // Only the object database is measured: walking the working tree, issue
+    // exports and release assets on every run would cost more than the fetch.
+    Ok((refs, directory_size(&repo.path().join("objects"))?))
+}
+
+fn directory_size(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            size += directory_size(&entry.path())?;
+        } else if file_type.is_file() {
+            size += entry.metadata()?.len();
         }
     }
+    Ok(size)
+}
+
+pub fn cleanup_staging_dirs(output_dir: &Path) -> Result<usize> {
+    let staging_dir = output_dir.join(STAGING_DIR);
+    if !staging_dir.is_dir() {
+        return Ok(0);
+    }
+
+    let mut removed = 0;
+    for owner_dir in fs::read_dir(&staging_dir)? {
+        let owner_dir = owner_dir?;
+        if !owner_dir.file_type()?.is_dir() {
+            continue;
+        }
+
+        for entry in fs::read_dir(owner_dir.path())? {
+            warn!("Removing interrupted clone {}", entry?.path().display());
+            removed += 1;
+        }
+    }
+
+    fs::remove_dir_all(&staging_dir)?;
+    Ok(removed)
+}
+
+fn update_submodules_recursive(
+    repo: &git2::Repository,
+    full_name: Option<&str>,
+    credentials: &GitCredentials,
+    local_repos: &LocalRepositories,
+) -> Result<()> {
+    for mut submodule in repo.submodules()? {
+        let Some(name) = submodule.name().map(str::to_string) else {
+            continue;
+        };
+        let submodule_full_name = submodule
+            .url()
+            .and_then(|url| submodule_full_name(url, full_name, &local_repos.hosts));
+
+        submodule.init(false)?;
+
+        let url_key = format!("submodule.{name}.url");
+        let upstream_url = repo.config()?.get_string(&url_key).ok();
+        let local_copy = submodule_full_name
+            .as_ref()
+            .and_then(|name| local_repos.paths.get(&name.to_lowercase()))
+            .filter(|path| path.join(".git").is_dir() || path.join("HEAD").is_file())
+            .filter(|_| upstream_url.is_some());
+
+        if let Some(local_copy) = local_copy {
+            debug!(
+                "Using local copy {} for submodule {}",
+                local_copy.display(),
+                name
+            );
+            set_submodule_url(repo, &name, &local_copy.to_string_lossy())?;
+            submodule = repo.find_submodule(&name)?;
+        }
+
+        let mut fetch_options = FetchOptions::new();
+        fetch_options.remote_callbacks(credentials.remote_callbacks());
+        let mut update_options = git2::SubmoduleUpdateOptions::new();
+        update_options.fetch(fetch_options);
+
+        let updated = submodule.update(true, Some(&mut update_options));
+
+        // The local copy is only a source for this fetch; the backup keeps
+        // pointing at the real upstream.
+        if let (Some(_), Some(upstream_url)) = (local_copy, &upstream_url) {
+            set_submodule_url(repo, &name, upstream_url)?;
+        }
+        updated?;
+
+        let submodule_repo = submodule.open()?;
+        update_submodules_recursive(
+            &submodule_repo,
+            submodule_full_name.as_deref(),
+            credentials,
+            local_repos,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn set_submodule_url(repo: &git2::Repository, name: &str, url: &str) -> Result<()> {
+    repo.config()?
+        .set_str(&format!("submodule.{name}.url"), url)?;
+    if let Ok(submodule_repo) = repo.find_submodule(name).and_then(|s| s.open()) {
+        submodule_repo.remote_set_url("origin", url)?;
+    }
+    Ok(())
+}
+
+// Resolves a submodule URL to the `owner/name` of a repository on one of the
+// exported hosts. Relative URLs are resolved against the parent repository.
+fn submodule_full_name(
+    url: &str,
+    parent_full_name: Option<&str>,
+    hosts: &HashSet<String>,
+) -> Option<String> {
+    let url = url.trim_end_matches('/');
+    let url = url.strip_suffix(".git").unwrap_or(url);
+
+    let path = if url.starts_with("./") || url.starts_with("../") {
+        let mut segments: Vec<&str> = parent_full_name?.split('/').collect();
+        for segment in url.split('/') {
+            match segment {
+                "." => {}
+                ".." => {
+                    segments.pop()?;
+                }
+                segment => segments.push(segment),
+            }
+        }
+        segments.join("/")
+    } else {
+        let (host, path) = parse_remote_url(url)?;
+        if !hosts.contains(&host) {
+            return None;
+        }
+        path
+    };
+
+    let (owner, name) = path.split_once('/')?;
+    (!owner.is_empty() && !name.is_empty() && !name.contains('/'))
+        .then(|| format!("{owner}/{name}"))
+}
+
+// Splits an absolute git URL (`scheme://host/path` or scp-style
+// `user@host:path`) into its lowercase host and its path.
+fn parse_remote_url(url: &str) -> Option<(String, String)> {
+    if url.contains("://") {
+        let parsed = url::Url::parse(url).ok()?;
+        let host = parsed.host_str()?.to_ascii_lowercase();
+        return Some((host, parsed.path().trim_matches('/').to_string()));
+    }
+
+    let (authority, path) = url.split_once(':')?;
+    if authority.contains('/') || path.starts_with("//") {
+        return None;
+    }
+    let host = authority.rsplit('@').next()?;
+    (!host.is_empty()).then(|| {
+        (
+            host.to_ascii_lowercase(),
+            path.trim_matches('/').to_string(),
+        )
+    })
+}
+
+// Only a remote that does not exist counts as a missing wiki; missing refs or
+// objects in a wiki that does exist are real failures.
+fn is_missing_remote(error: &git2::Error) -> bool {
+    match error.class() {
+        git2::ErrorClass::Http => http_status(error) == Some(404),
+        git2::ErrorClass::Ssh | git2::ErrorClass::Net => error
+            .message()
+            .to_ascii_lowercase()
+            .contains("repository not found"),
+        // How the local transport reports a file:// remote that does not exist.
+        git2::ErrorClass::Os => error.message().starts_with("failed to resolve path"),
+        _ => false,
+    }
+}
+
+pub fn export_data_dir(repo_path: &Path) -> Result<PathBuf> {
+    let data_dir = repo_path.join(EXPORT_DATA_DIR);
+    fs::create_dir_all(&data_dir)?;
+
+    let git_dir = if repo_path.join(".git").is_dir() {
+        repo_path.join(".git")
+    } else {
+        repo_path.to_path_buf()
+    };
+    let exclude_path = git_dir.join("info").join("exclude");
+    let pattern = format!("/{EXPORT_DATA_DIR}/");
+
+    if git_dir.join("HEAD").exists() {
+        let existing = fs::read_to_string(&exclude_path).unwrap_or_default();
+        if !existing.lines().any(|line| line == pattern) {
+            fs::create_dir_all(git_dir.join("info"))?;
+            let separator = if existing.is_empty() || existing.ends_with('\n') {
+                ""
+            } else {
+                "\n"
+            };
+            fs::write(&exclude_path, format!("{existing}{separator}{pattern}\n"))?;
+        }
+    }
+
+    Ok(data_dir)
 }
 
 pub async fn check_disk_space(_path: &Path, _required_bytes: u64) -> Result<()> {
@@ -234,3 +896,59 @@ pub async fn check_disk_space(_path: &Path, _required_bytes: u64) -> Result<()>
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hosts() -> HashSet<String> {
+        HashSet::from(["github.com".to_string()])
+    }
+
+    #[test]
+    fn resolves_relative_submodule_urls_against_parent() {
+        let parent = Some("acme/app");
+        assert_eq!(
+            submodule_full_name("../lib.git", parent, &hosts()),
+            Some("acme/lib".to_string())
+        );
+        assert_eq!(
+            submodule_full_name("../../other/lib", parent, &hosts()),
+            Some("other/lib".to_string())
+        );
+        assert_eq!(submodule_full_name("./vendor/lib", parent, &hosts()), None);
+        assert_eq!(submodule_full_name("../lib", None, &hosts()), None);
+    }
+
+    #[test]
+    fn resolves_absolute_urls_on_exported_hosts() {
+        assert_eq!(
+            submodule_full_name("https://github.com/acme/tool.git", None, &hosts()),
+            Some("acme/tool".to_string())
+        );
+        assert_eq!(
+            submodule_full_name("git@github.com:acme/tool.git", None, &hosts()),
+            Some("acme/tool".to_string())
+        );
+        assert_eq!(
+            submodule_full_name("ssh://git@GitHub.com/acme/tool", None, &hosts()),
+            Some("acme/tool".to_string())
+        );
+    }
+
+    #[test]
+    fn ignores_submodules_on_foreign_hosts() {
+        assert_eq!(
+            submodule_full_name("https://gitlab.com/acme/tool", None, &hosts()),
+            None
+        );
+        assert_eq!(
+            submodule_full_name("git@gitlab.com:acme/tool.git", None, &hosts()),
+            None
+        );
+        assert_eq!(
+            submodule_full_name("https://github.com/acme/tool/extra", None, &hosts()),
+            None
+        );
+    }
+}
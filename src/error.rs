@@ -1,7 +1,8 @@
+use git2::{ErrorClass, ErrorCode};
+use std::io::ErrorKind;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
-#[allow(dead_code)]
 pub enum GhExportError {
     #[error("GitHub API error: {0}")]
     GitHubApi(String),
@@ -15,6 +16,15 @@ pub enum GhExportError {
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
+    #[error("Not found: {0}")]
+    NotFound(String),
+
+    #[error("Transfer error: {0}")]
+    Transfer(String),
+
+    #[error("Corrupt repository: {0}")]
+    Corrupt(String),
+
     #[error("Configuration error: {0}")]
     Config(String),
 
@@ -39,8 +49,304 @@ pub enum GhExportError {
     #[error("Disk space insufficient: need {needed} bytes, have {available} bytes")]
     InsufficientSpace { needed: u64, available: u64 },
 
+    #[error("{failed} repositories failed to export ({succeeded} succeeded)")]
+    ExportFailed {
+        failed: usize,
+        succeeded: usize,
+        cause: Option<FailureCause>,
+    },
+
+    #[error("Export interrupted with {cancelled} repositories not started")]
+    Interrupted { cancelled: usize },
+
+    #[error("Verification failed: {0}")]
+    Verification(String),
+
     #[error("Dialog error: {0}")]
     Dialog(#[from] dialoguer::Error),
 }
 
+impl GhExportError {
+    // libgit2 reports every failure as a `git2::Error` and reqwest as a
+    // `reqwest::Error`; sort them into the variants that decide whether
+    // retrying can help.
+    pub fn classify(self) -> Self {
+        let error = match self {
+            GhExportError::Git(error) => error,
+            GhExportError::Network(error) => {
+                return match error.status().map(|status| status.as_u16()) {
+                    Some(401 | 403) => GhExportError::Auth(error.to_string()),
+                    Some(404) => GhExportError::NotFound(error.to_string()),
+                    _ => GhExportError::Network(error),
+                };
+            }
+            other => return other,
+        };
+
+        let message = error.message().to_string();
+        let remote = matches!(
+            error.class(),
+            ErrorClass::Net | ErrorClass::Http | ErrorClass::Ssh | ErrorClass::Ssl
+        );
+        let status = http_status(&error);
+
+        if error.code() == ErrorCode::Auth || matches!(status, Some(401 | 403)) {
+            GhExportError::Auth(message)
+        } else if remote && (error.code() == ErrorCode::NotFound || status == Some(404)) {
+            GhExportError::NotFound(message)
+        } else if error.code() == ErrorCode::User {
+            GhExportError::Git(error)
+        } else if remote {
+            GhExportError::Transfer(message)
+        } else if matches!(
+            error.class(),
+            ErrorClass::Odb
+                | ErrorClass::Zlib
+                | ErrorClass::Object
+                | ErrorClass::Index
+                | ErrorClass::Tree
+                | ErrorClass::Reference
+                | ErrorClass::Repository
+        ) {
+            GhExportError::Corrupt(message)
+        } else if matches!(error.class(), ErrorClass::Os | ErrorClass::Filesystem) {
+            GhExportError::Io(std::io::Error::new(os_error_kind(&message), message))
+        } else {
+            GhExportError::Git(error)
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            // A body that fails to decode or a 4xx will not change on retry.
+            GhExportError::Network(e) => {
+                !e.is_decode()
+                    && !e.is_builder()
+                    && e.status().is_none_or(|status| status.is_server_error())
+            }
+            GhExportError::Transfer(_) => true,
+            GhExportError::Io(e) => matches!(
+                e.kind(),
+                ErrorKind::TimedOut
+                    | ErrorKind::Interrupted
+                    | ErrorKind::ConnectionReset
+                    | ErrorKind::ConnectionAborted
+                    | ErrorKind::UnexpectedEof
+            ),
+            _ => false,
+        }
+    }
+
+    pub fn is_storage_full(&self) -> bool {
+        match self {
+            GhExportError::InsufficientSpace { .. } => true,
+            GhExportError::Io(e) => e.kind() == ErrorKind::StorageFull,
+            _ => false,
+        }
+    }
+
+    pub fn category(&self) -> &'static str {
+        match self {
+            GhExportError::Auth(_) => "auth",
+            GhExportError::NotFound(_) => "not found",
+            GhExportError::Network(_) | GhExportError::Transfer(_) => "network",
+            GhExportError::Io(_) | GhExportError::InsufficientSpace { .. } => "disk",
+            GhExportError::Corrupt(_) => "corrupt repository",
+            GhExportError::RateLimit(_) => "rate limit",
+            _ => "other",
+        }
+    }
+
+    // Documented in the README; monitoring relies on these staying stable.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            GhExportError::ExportFailed {
+                cause: Some(FailureCause::Auth),
+                ..
+            } => 5,
+            GhExportError::ExportFailed {
+                cause: Some(FailureCause::RateLimit),
+                ..
+            } => 6,
+            GhExportError::ExportFailed {
+                cause: Some(FailureCause::DiskFull),
+                ..
+            } => 7,
+            GhExportError::ExportFailed { succeeded: 0, .. } => 4,
+            GhExportError::ExportFailed { .. } => 3,
+            GhExportError::Auth(_) => 5,
+            GhExportError::RateLimit(_) => 6,
+            GhExportError::InsufficientSpace { .. } => 7,
+            GhExportError::Io(e) if e.kind() == ErrorKind::StorageFull => 7,
+            GhExportError::Verification(_) => 8,
+            GhExportError::Interrupted { .. } => 130,
+            // EX_CONFIG from sysexits.h
+            GhExportError::Config(_) => 78,
+            _ => 1,
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, GhExportError>;
+
+// A cause with its own exit code, reported for a run when every failed
+// repository failed because of it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureCause {
+    Auth,
+    RateLimit,
+    DiskFull,
+}
+
+impl FailureCause {
+    pub fn shared<'a>(errors: impl IntoIterator<Item = &'a GhExportError>) -> Option<Self> {
+        let mut causes = errors.into_iter().map(|error| match error {
+            GhExportError::Auth(_) => Some(FailureCause::Auth),
+            GhExportError::RateLimit(_) => Some(FailureCause::RateLimit),
+            error if error.is_storage_full() => Some(FailureCause::DiskFull),
+            _ => None,
+        });
+        let first = causes.next()??;
+        causes.all(|cause| cause == Some(first)).then_some(first)
+    }
+}
+
+// libgit2 only passes OS errors on as text, with strerror() appended.
+fn os_error_kind(message: &str) -> ErrorKind {
+    const STORAGE_FULL: &[&str] = &[
+        "No space left on device",
+        "There is not enough space on the disk",
+    ];
+    if STORAGE_FULL.iter().any(|text| message.contains(text)) {
+        ErrorKind::StorageFull
+    } else {
+        ErrorKind::Other
+    }
+}
+
+// libgit2 has no error code for HTTP statuses; its HTTP transport reports
+// them as "unexpected http status code: <status>".
+pub fn http_status(error: &git2::Error) -> Option<u16> {
+    if error.class() != ErrorClass::Http {
+        return None;
+    }
+    let (_, status) = error.message().rsplit_once("status code:")?;
+    status.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git_error(class: ErrorClass, message: &str) -> GhExportError {
+        GhExportError::Git(git2::Error::new(ErrorCode::GenericError, class, message)).classify()
+    }
+
+    #[test]
+    fn test_http_statuses_from_libgit2() {
+        let forbidden = git_error(ErrorClass::Http, "unexpected http status code: 403");
+        assert!(matches!(forbidden, GhExportError::Auth(_)));
+
+        let missing = git_error(ErrorClass::Http, "unexpected http status code: 404");
+        assert!(matches!(missing, GhExportError::NotFound(_)));
+
+        let unavailable = git_error(ErrorClass::Http, "unexpected http status code: 503");
+        assert!(matches!(unavailable, GhExportError::Transfer(_)));
+        assert!(unavailable.is_retryable());
+    }
+
+    #[test]
+    fn test_full_disk_from_libgit2_keeps_its_kind() {
+        let error = git_error(
+            ErrorClass::Os,
+            "failed to write to pack: No space left on device",
+        );
+        assert!(error.is_storage_full());
+        assert_eq!(error.exit_code(), 7);
+
+        let error = git_error(ErrorClass::Os, "failed to open file: Permission denied");
+        assert!(!error.is_storage_full());
+    }
+
+    #[test]
+    fn test_shared_failure_cause() {
+        let auth = || GhExportError::Auth("Bad credentials".to_string());
+        let full = || GhExportError::Io(std::io::Error::from(ErrorKind::StorageFull));
+        let transfer = || GhExportError::Transfer("connection reset".to_string());
+        let rate_limit = || GhExportError::RateLimit("2024-01-01 00:00:00 UTC".to_string());
+
+        assert_eq!(
+            FailureCause::shared(&[auth(), auth()]),
+            Some(FailureCause::Auth)
+        );
+        assert_eq!(
+            FailureCause::shared(&[full(), full()]),
+            Some(FailureCause::DiskFull)
+        );
+        assert_eq!(
+            FailureCause::shared(&[rate_limit(), rate_limit()]),
+            Some(FailureCause::RateLimit)
+        );
+        assert_eq!(FailureCause::shared(&[auth(), transfer()]), None);
+        assert_eq!(FailureCause::shared(&[rate_limit(), auth()]), None);
+        assert_eq!(FailureCause::shared(&[full(), auth()]), None);
+        assert_eq!(FailureCause::shared(&[]), None);
+
+        let failed = |cause| GhExportError::ExportFailed {
+            failed: 2,
+            succeeded: 1,
+            cause,
+        };
+        assert_eq!(failed(Some(FailureCause::Auth)).exit_code(), 5);
+        assert_eq!(failed(Some(FailureCause::RateLimit)).exit_code(), 6);
+        assert_eq!(failed(Some(FailureCause::DiskFull)).exit_code(), 7);
+        assert_eq!(failed(None).exit_code(), 3);
+    }
+
+    #[test]
+    fn test_status_like_numbers_in_messages_are_ignored() {
+        let error = git_error(
+            ErrorClass::Reference,
+            "reference 'refs/heads/fix-401-403' not found",
+        );
+        assert!(matches!(error, GhExportError::Corrupt(_)));
+
+        let error = git_error(ErrorClass::Net, "received 4031 bytes before EOF");
+        assert!(matches!(error, GhExportError::Transfer(_)));
+    }
+
+    #[tokio::test]
+    async fn test_reqwest_errors_by_status_and_kind() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/forbidden")
+            .with_status(403)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/garbled")
+            .with_body("not json")
+            .create_async()
+            .await;
+
+        let forbidden = reqwest::get(format!("{}/forbidden", server.url()))
+            .await
+            .unwrap()
+            .error_for_status()
+            .unwrap_err();
+        assert!(matches!(
+            GhExportError::from(forbidden).classify(),
+            GhExportError::Auth(_)
+        ));
+
+        let garbled = reqwest::get(format!("{}/garbled", server.url()))
+            .await
+            .unwrap()
+            .json::<serde_json::Value>()
+            .await
+            .unwrap_err();
+        let error = GhExportError::from(garbled).classify();
+        assert!(matches!(error, GhExportError::Network(_)));
+        assert!(!error.is_retryable());
+    }
+}
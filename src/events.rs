@@ -0,0 +1,120 @@
+use crate::download::{DownloadResult, WikiStatus};
+use serde::Serialize;
+use std::io::Write;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    Discovery {
+        discovered: usize,
+        selected: usize,
+        estimated_bytes: u64,
+    },
+    RepoStarted {
+        repository: &'a str,
+    },
+    Progress {
+        repository: &'a str,
+        received_objects: u32,
+        total_objects: u32,
+    },
+    RepoFinished {
+        repository: &'a str,
+        result: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reason: Option<&'a str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error_category: Option<&'static str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        attempts: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        wiki: Option<&'static str>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        lfs_objects: Option<usize>,
+        #[serde(skip_serializing_if = "<[String]>::is_empty")]
+        diverged_branches: &'a [String],
+    },
+    Summary {
+        total: usize,
+        successful: usize,
+        failed: usize,
+        skipped: usize,
+        cancelled: usize,
+        duration_seconds: u64,
+        interrupted: bool,
+    },
+    Verified {
+        repository: &'a str,
+        result: &'static str,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reason: Option<&'a str>,
+        #[serde(skip_serializing_if = "<[String]>::is_empty")]
+        stale: &'a [String],
+        #[serde(skip_serializing_if = "<[String]>::is_empty")]
+        warnings: &'a [String],
+    },
+    VerifySummary {
+        total: usize,
+        ok: usize,
+        stale: usize,
+        corrupted: usize,
+    },
+}
+
+#[derive(Serialize)]
+struct Envelope<'a> {
+    timestamp: chrono::DateTime<chrono::Utc>,
+    #[serde(flatten)]
+    event: &'a Event<'a>,
+}
+
+impl<'a> Event<'a> {
+    pub fn repo_finished(repository: &'a str, result: &'a DownloadResult) -> Self {
+        let (outcome, reason, error, error_category, attempts) = match result {
+            DownloadResult::Success(_) => ("success", None, None, None, None),
+            DownloadResult::Skipped(reason) => ("skipped", Some(reason.as_str()), None, None, None),
+            DownloadResult::Cancelled => ("cancelled", None, None, None, None),
+            DownloadResult::Failed(failure) => (
+                "failed",
+                None,
+                Some(failure.to_string()),
+                Some(failure.error.category()),
+                Some(failure.attempts),
+            ),
+        };
+        let report = match result {
+            DownloadResult::Success(report) => Some(report),
+            _ => None,
+        };
+
+        Event::RepoFinished {
+            repository,
+            result: outcome,
+            reason,
+            error,
+            error_category,
+            attempts,
+            wiki: report
+                .and_then(|report| report.wiki)
+                .map(|status| match status {
+                    WikiStatus::Backed => "backed_up",
+                    WikiStatus::NotInitialized => "not_initialized",
+                }),
+            lfs_objects: report.map(|report| report.lfs_objects),
+            diverged_branches: report.map_or(&[], |report| &report.diverged_branches),
+        }
+    }
+
+    pub fn emit(&self) {
+        let envelope = Envelope {
+            timestamp: chrono::Utc::now(),
+            event: self,
+        };
+        if let Ok(line) = serde_json::to_string(&envelope) {
+            let mut stdout = std::io::stdout().lock();
+            let _ = writeln!(stdout, "{line}");
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use crate::config::{Config, Visibility};
+use crate::error::{GhExportError, Result};
+use crate::github::Repository;
+use glob::{MatchOptions, Pattern};
+use regex::Regex;
+
+const REGEX_PREFIX: &str = "re:";
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: false,
+    require_literal_separator: false,
+    require_literal_leading_dot: false,
+};
+
+enum NamePattern {
+    Glob(Pattern),
+    Regex(Regex),
+}
+
+impl NamePattern {
+    fn parse(pattern: &str) -> Result<Self> {
+        if let Some(expression) = pattern.strip_prefix(REGEX_PREFIX) {
+            Regex::new(expression)
+                .map(NamePattern::Regex)
+                .map_err(|e| GhExportError::Config(format!("Invalid regex '{expression}': {e}")))
+        } else {
+            Pattern::new(pattern)
+                .map(NamePattern::Glob)
+                .map_err(|e| GhExportError::Config(format!("Invalid pattern '{pattern}': {e}")))
+        }
+    }
+
+    // A plain word keeps matching anywhere in the name, as `--filter` always has.
+    fn parse_name_filter(pattern: &str) -> Result<Self> {
+        if pattern.starts_with(REGEX_PREFIX) || pattern.contains(['*', '?', '[']) {
+            Self::parse(pattern)
+        } else {
+            Self::parse(&format!("*{pattern}*"))
+        }
+    }
+
+    fn matches(&self, full_name: &str) -> bool {
+        match self {
+            NamePattern::Glob(pattern) => pattern.matches_with(full_name, MATCH_OPTIONS),
+            NamePattern::Regex(regex) => regex.is_match(full_name),
+        }
+    }
+}
+
+pub struct RepoFilter {
+    name_filter: Option<NamePattern>,
+    include: Vec<NamePattern>,
+    exclude: Vec<NamePattern>,
+    languages: Vec<String>,
+    topics: Vec<String>,
+    visibility: Visibility,
+    min_size_kb: Option<u64>,
+    max_size_kb: Option<u64>,
+}
+
+impl RepoFilter {
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let parse_all = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| NamePattern::parse(pattern))
+                .collect::<Result<Vec<_>>>()
+        };
+
+        Ok(Self {
+            name_filter: config
+                .name_filter
+                .as_deref()
+                .map(NamePattern::parse_name_filter)
+                .transpose()?,
+            include: parse_all(&config.include)?,
+            exclude: parse_all(&config.exclude)?,
+            languages: config.languages.iter().map(|l| l.to_lowercase()).collect(),
+            topics: config.topics.iter().map(|t| t.to_lowercase()).collect(),
+            visibility: config.visibility,
+            min_size_kb: config.min_size_kb,
+            max_size_kb: config.max_size_kb,
+        })
+    }
+
+    pub fn matches(&self, repo: &Repository) -> bool {
+        if self
+            .name_filter
+            .as_ref()
+            .is_some_and(|pattern| !pattern.matches(&repo.full_name))
+        {
+            return false;
+        }
+
+        if !self.include.is_empty() && !self.include.iter().any(|p| p.matches(&repo.full_name)) {
+            return false;
+        }
+
+        if self.exclude.iter().any(|p| p.matches(&repo.full_name)) {
+            return false;
+        }
+
+        if !self.languages.is_empty()
+            && !repo
+                .language
+                .as_ref()
+                .is_some_and(|language| self.languages.contains(&language.to_lowercase()))
+        {
+            return false;
+        }
+
+        if !self.topics.is_empty()
+            && !repo
+                .topics
+                .iter()
+                .any(|topic| self.topics.contains(&topic.to_lowercase()))
+        {
+            return false;
+        }
+
+        match self.visibility {
+            Visibility::All => {}
+            Visibility::Public if repo.private => return false,
+            Visibility::Private if !repo.private => return false,
+            _ => {}
+        }
+
+        if self.min_size_kb.is_some_and(|min| repo.size < min) {
+            return false;
+        }
+
+        if self.max_size_kb.is_some_and(|max| repo.size > max) {
+            return false;
+        }
+
+        true
+    }
+}
+
+pub fn parse_size_kb(value: &str) -> std::result::Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{value}'"))?;
+    let multiplier = match unit.trim().to_ascii_uppercase().as_str() {
+        "B" => 1.0 / 1024.0,
+        "" | "K" | "KB" => 1.0,
+        "M" | "MB" => 1024.0,
+        "G" | "GB" => 1024.0 * 1024.0,
+        _ => {
+            return Err(format!(
+                "invalid size unit in '{value}' (use B, KB, MB or GB)"
+            ))
+        }
+    };
+
+    Ok((number * multiplier).ceil() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn repository(full_name: &str) -> Repository {
+        let (owner, name) = full_name.split_once('/').unwrap();
+        serde_json::from_value(serde_json::json!({
+            "id": 1,
+            "name": name,
+            "full_name": full_name,
+            "owner": {"login": owner, "id": 1, "type": "User"},
+            "private": false,
+            "html_url": "",
+            "description": null,
+            "fork": false,
+            "created_at": "",
+            "updated_at": "",
+            "pushed_at": null,
+            "clone_url": "",
+            "ssh_url": "",
+            "size": 100,
+            "stargazers_count": 0,
+            "watchers_count": 0,
+            "language": "Rust",
+            "archived": false,
+            "disabled": false,
+            "default_branch": "main",
+            "topics": ["backup", "cli"]
+        }))
+        .unwrap()
+    }
+
+    fn filter(configure: impl FnOnce(&mut Config)) -> RepoFilter {
+        let mut config = Config::default();
+        configure(&mut config);
+        RepoFilter::from_config(&config).unwrap()
+    }
+
+    #[test]
+    fn exclude_wins_over_include() {
+        let filter = filter(|config| {
+            config.include = vec!["acme/*".to_string()];
+            config.exclude = vec!["*-archive".to_string()];
+        });
+
+        assert!(filter.matches(&repository("acme/tool")));
+        assert!(!filter.matches(&repository("acme/tool-archive")));
+        assert!(!filter.matches(&repository("other/tool")));
+    }
+
+    #[test]
+    fn regex_patterns_match_full_name() {
+        let filter = filter(|config| {
+            config.include = vec!["re:^acme/(api|web)$".to_string()];
+        });
+
+        assert!(filter.matches(&repository("acme/api")));
+        assert!(filter.matches(&repository("acme/web")));
+        assert!(!filter.matches(&repository("acme/api-docs")));
+        assert!(RepoFilter::from_config(&Config {
+            include: vec!["re:(".to_string()],
+            ..Config::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn name_filter_narrows_includes() {
+        let filter = filter(|config| {
+            config.include = vec!["acme/*".to_string(), "other/*".to_string()];
+            config.name_filter = Some("Tool".to_string());
+        });
+
+        assert!(filter.matches(&repository("acme/my-tool")));
+        assert!(filter.matches(&repository("other/tools")));
+        assert!(!filter.matches(&repository("acme/api")));
+        assert!(!filter.matches(&repository("third/tool")));
+    }
+
+    #[test]
+    fn name_filter_accepts_globs_and_regexes() {
+        let glob = filter(|config| config.name_filter = Some("acme/*".to_string()));
+        assert!(glob.matches(&repository("acme/tool")));
+        assert!(!glob.matches(&repository("other/acme")));
+
+        let regex = filter(|config| config.name_filter = Some("re:^acme/".to_string()));
+        assert!(regex.matches(&repository("acme/tool")));
+        assert!(!regex.matches(&repository("other/acme")));
+
+        assert!(RepoFilter::from_config(&Config {
+            name_filter: Some("re:(".to_string()),
+            ..Config::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn language_topic_and_visibility_predicates() {
+        let mut private = repository("acme/secret");
+        private.private = true;
+
+        let rust_only = filter(|config| config.languages = vec!["rust".to_string()]);
+        assert!(rust_only.matches(&repository("acme/tool")));
+        let go_only = filter(|config| config.languages = vec!["go".to_string()]);
+        assert!(!go_only.matches(&repository("acme/tool")));
+
+        let topic = filter(|config| config.topics = vec!["BACKUP".to_string()]);
+        assert!(topic.matches(&repository("acme/tool")));
+        let other_topic = filter(|config| config.topics = vec!["web".to_string()]);
+        assert!(!other_topic.matches(&repository("acme/tool")));
+
+        let private_only = filter(|config| config.visibility = Visibility::Private);
+        assert!(private_only.matches(&private));
+        assert!(!private_only.matches(&repository("acme/tool")));
+        let public_only = filter(|config| config.visibility = Visibility::Public);
+        assert!(!public_only.matches(&private));
+    }
+
+    #[test]
+    fn size_bounds_are_inclusive() {
+        let exact = filter(|config| {
+            config.min_size_kb = Some(100);
+            config.max_size_kb = Some(100);
+        });
+        assert!(exact.matches(&repository("acme/tool")));
+
+        let too_small = filter(|config| config.min_size_kb = Some(101));
+        assert!(!too_small.matches(&repository("acme/tool")));
+    }
+
+    #[test]
+    fn parses_sizes_in_kilobytes() {
+        assert_eq!(parse_size_kb("500"), Ok(500));
+        assert_eq!(parse_size_kb("500KB"), Ok(500));
+        assert_eq!(parse_size_kb("1.5 MB"), Ok(1536));
+        assert_eq!(parse_size_kb("2gb"), Ok(2 * 1024 * 1024));
+        assert_eq!(parse_size_kb("100B"), Ok(1));
+        assert!(parse_size_kb("10TB").is_err());
+        assert!(parse_size_kb("big").is_err());
+    }
+}
@@ -1,19 +1,50 @@
+use crate::cache::{CachedResponse, HttpCache};
 use crate::error::{GhExportError, Result};
-use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, USER_AGENT};
+use base64::Engine;
+use futures::stream::{self, Stream, TryStreamExt};
+use reqwest::header::{
+    HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE,
+    IF_NONE_MATCH, LAST_MODIFIED, LINK, USER_AGENT,
+};
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::time::Duration;
 use tracing::{debug, warn};
 
-const GITHUB_API_BASE: &str = "https://api.github.com";
+pub const DEFAULT_API_BASE: &str = "https://api.github.com";
 const USER_AGENT_STRING: &str = "gh-export/0.1.0";
+const ASSET_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+const PER_PAGE: usize = 100;
+const MAX_RETRIES: u32 = 5;
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+const SECONDARY_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
 
 #[derive(Debug, Clone)]
 pub struct GitHubClient {
     client: reqwest::Client,
-    #[allow(dead_code)]
+    // Carries no token, for URLs handed out by GitHub that point at other
+    // hosts, such as LFS object storage.
+    downloads: reqwest::Client,
+    api_base: String,
+    git_host: Option<url::Url>,
+    max_rate_limit_wait: Duration,
+    cache: Option<HttpCache>,
     token: String,
 }
 
+struct ApiResponse {
+    status: StatusCode,
+    link: Option<String>,
+    body: String,
+}
+
+enum RetryDelay {
+    RateLimited { wait: Duration, reset: String },
+    Transient(Duration),
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Repository {
     pub id: u64,
@@ -36,6 +67,12 @@ pub struct Repository {
     pub archived: bool,
     pub disabled: bool,
     pub default_branch: String,
+    #[serde(default)]
+    pub has_issues: bool,
+    #[serde(default)]
+    pub has_wiki: bool,
+    #[serde(default)]
+    pub topics: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,19 +83,49 @@ pub struct Owner {
     pub owner_type: String,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    pub id: u64,
+    pub tag_name: String,
+    pub assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleaseAsset {
+    pub id: u64,
+    pub name: String,
+    pub size: u64,
+    pub url: String,
+    pub digest: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Organization {
+    pub login: String,
+    #[allow(dead_code)]
+    pub id: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Branch {
+    pub name: String,
+    pub commit: BranchCommit,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct BranchCommit {
+    pub sha: String,
+}
+
 #[derive(Debug, Deserialize)]
-#[allow(dead_code)]
 pub struct RateLimitResponse {
     pub rate: RateLimit,
 }
 
 #[derive(Debug, Deserialize)]
-#[allow(dead_code)]
 pub struct RateLimit {
     pub limit: u64,
     pub remaining: u64,
-    pub reset: u64,
-    pub used: u64,
 }
 
 #[derive(Debug, Deserialize)]
@@ -75,7 +142,16 @@ pub struct User {
 }
 
 impl GitHubClient {
-    pub fn new(token: String) -> Result<Self> {
+    pub fn new(
+        token: String,
+        api_base: &str,
+        git_host: Option<&str>,
+        max_rate_limit_wait: Duration,
+        cache: Option<HttpCache>,
+    ) -> Result<Self> {
+        let api_base = normalize_api_base(api_base)?;
+        let git_host = git_host.map(parse_git_host).transpose()?;
+
         let mut headers = HeaderMap::new();
         headers.insert(
             AUTHORIZATION,
@@ -92,85 +168,396 @@ impl GitHubClient {
             .default_headers(headers)
             .timeout(Duration::from_secs(30))
             .build()?;
+        let downloads = reqwest::Client::builder()
+            .user_agent(USER_AGENT_STRING)
+            .build()?;
 
-        Ok(Self { client, token })
+        Ok(Self {
+            client,
+            downloads,
+            api_base,
+            git_host,
+            max_rate_limit_wait,
+            cache,
+            token,
+        })
     }
 
     pub async fn get_authenticated_user(&self) -> Result<User> {
-        let url = format!("{GITHUB_API_BASE}/user");
-        let response = self.client.get(&url).send().await?;
+        let url = format!("{}/user", self.api_base);
+        let response = self.get_cached(&url).await?;
+
+        if response.status == StatusCode::UNAUTHORIZED {
+            return Err(GhExportError::Auth(format!(
+                "GitHub rejected the token: {}",
+                response.body
+            )));
+        }
+
+        if !response.status.is_success() {
+            return Err(GhExportError::GitHubApi(format!(
+                "Failed to get user info: {} - {}",
+                response.status, response.body
+            )));
+        }
+
+        Ok(serde_json::from_str(&response.body)?)
+    }
+
+    pub async fn list_user_repositories(&self, username: &str) -> Result<Vec<Repository>> {
+        let url = format!("{}/users/{username}/repos", self.api_base);
+
+        let repositories = match self.get_paginated(&url, "list repositories").await {
+            Err(GhExportError::NotFound(_)) => {
+                self.get_paginated(
+                    &format!("{}/user/repos", self.api_base),
+                    "list repositories",
+                )
+                .await?
+            }
+            result => result?,
+        };
+
+        Ok(self.apply_git_host(repositories))
+    }
+
+    pub async fn get_repository(&self, full_name: &str) -> Result<Repository> {
+        self.get_repository_at(&format!("{}/repos/{full_name}", self.api_base), full_name)
+            .await
+    }
+
+    pub async fn get_repository_by_id(&self, id: u64) -> Result<Repository> {
+        self.get_repository_at(
+            &format!("{}/repositories/{id}", self.api_base),
+            &format!("#{id}"),
+        )
+        .await
+    }
+
+    pub async fn list_branches(&self, full_name: &str) -> Result<Vec<Branch>> {
+        self.get_paginated(
+            &format!("{}/repos/{full_name}/branches", self.api_base),
+            &format!("list branches for {full_name}"),
+        )
+        .await
+    }
+
+    pub async fn list_user_organizations(&self) -> Result<Vec<Organization>> {
+        self.get_paginated(
+            &format!("{}/user/orgs", self.api_base),
+            "list organizations",
+        )
+        .await
+    }
+
+    pub async fn list_org_repositories(&self, org: &str) -> Result<Vec<Repository>> {
+        let repositories = self
+            .get_paginated(
+                &format!("{}/orgs/{org}/repos?type=all", self.api_base),
+                &format!("list repositories for organization {org}"),
+            )
+            .await?;
+
+        Ok(self.apply_git_host(repositories))
+    }
+
+    pub async fn list_issues(
+        &self,
+        full_name: &str,
+        since: Option<&str>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.get_paginated(
+            &with_since(
+                format!(
+                    "{}/repos/{full_name}/issues?state=all&sort=updated&direction=asc",
+                    self.api_base
+                ),
+                since,
+            ),
+            &format!("list issues for {full_name}"),
+        )
+        .await
+    }
+
+    pub fn list_pull_requests<'a>(
+        &'a self,
+        full_name: &str,
+    ) -> impl Stream<Item = Result<serde_json::Value>> + 'a {
+        self.paginate(
+            &format!(
+                "{}/repos/{full_name}/pulls?state=all&sort=updated&direction=desc",
+                self.api_base
+            ),
+            &format!("list pull requests for {full_name}"),
+        )
+    }
+
+    pub async fn list_issue_comments(
+        &self,
+        full_name: &str,
+        since: Option<&str>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.get_paginated(
+            &with_since(
+                format!("{}/repos/{full_name}/issues/comments", self.api_base),
+                since,
+            ),
+            &format!("list issue comments for {full_name}"),
+        )
+        .await
+    }
+
+    pub async fn list_review_comments(
+        &self,
+        full_name: &str,
+        since: Option<&str>,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.get_paginated(
+            &with_since(
+                format!("{}/repos/{full_name}/pulls/comments", self.api_base),
+                since,
+            ),
+            &format!("list review comments for {full_name}"),
+        )
+        .await
+    }
+
+    pub async fn list_timeline(
+        &self,
+        full_name: &str,
+        number: u64,
+    ) -> Result<Vec<serde_json::Value>> {
+        self.get_paginated(
+            &format!(
+                "{}/repos/{full_name}/issues/{number}/timeline",
+                self.api_base
+            ),
+            &format!("list timeline events for {full_name}#{number}"),
+        )
+        .await
+    }
+
+    pub async fn list_releases(&self, full_name: &str) -> Result<Vec<serde_json::Value>> {
+        self.get_paginated(
+            &format!("{}/repos/{full_name}/releases", self.api_base),
+            &format!("list releases for {full_name}"),
+        )
+        .await
+    }
+
+    pub async fn get_release_asset(&self, asset: &ReleaseAsset) -> Result<reqwest::Response> {
+        let response = self
+            .send(
+                self.client
+                    .get(&asset.url)
+                    .header(ACCEPT, "application/octet-stream")
+                    .timeout(ASSET_DOWNLOAD_TIMEOUT),
+            )
+            .await?;
 
         if !response.status().is_success() {
             let status = response.status();
             let text = response.text().await.unwrap_or_default();
             return Err(GhExportError::GitHubApi(format!(
-                "Failed to get user info: {status} - {text}"
+                "Failed to download release asset {}: {status} - {text}",
+                asset.name
             )));
         }
 
-        Ok(response.json().await?)
+        Ok(response)
     }
 
-    pub async fn list_user_repositories(&self, username: &str) -> Result<Vec<Repository>> {
-        let mut repositories = Vec::new();
-        let mut page = 1;
-        let per_page = 100;
+    // The LFS batch API lives on the git host and takes the token as basic auth.
+    pub async fn post_lfs_batch<T: Serialize>(
+        &self,
+        url: &str,
+        media_type: &str,
+        body: &T,
+    ) -> Result<reqwest::Response> {
+        let credentials = base64::engine::general_purpose::STANDARD
+            .encode(format!("x-access-token:{}", self.token));
 
-        loop {
-            debug!("Fetching repositories page {}", page);
-            let url = format!(
-                "{GITHUB_API_BASE}/users/{username}/repos?per_page={per_page}&page={page}"
-            );
-
-            let response = self.client.get(&url).send().await?;
-
-            if response.status() == 404 {
-                let url = format!(
-                    "{GITHUB_API_BASE}/user/repos?per_page={per_page}&page={page}"
-                );
-                let response = self.client.get(&url).send().await?;
-
-                if !response.status().is_success() {
-                    let status = response.status();
-                    let text = response.text().await.unwrap_or_default();
-                    return Err(GhExportError::GitHubApi(format!(
-                        "Failed to list repositories: {status} - {text}"
-                    )));
-                }
+        self.send(
+            self.client
+                .post(url)
+                .header(ACCEPT, media_type)
+                .header(CONTENT_TYPE, media_type)
+                .header(AUTHORIZATION, format!("Basic {credentials}"))
+                .json(body),
+        )
+        .await
+    }
 
-                let repos: Vec<Repository> = response.json().await?;
-                let is_last_page = repos.len() < per_page;
-                repositories.extend(repos);
+    pub async fn download(
+        &self,
+        url: &str,
+        headers: &HashMap<String, String>,
+        timeout: Duration,
+    ) -> Result<reqwest::Response> {
+        let mut request = self.downloads.get(url).timeout(timeout);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+        self.send(request).await
+    }
 
-                if is_last_page {
-                    break;
-                }
-            } else if response.status().is_success() {
-                let repos: Vec<Repository> = response.json().await?;
-                let is_last_page = repos.len() < per_page;
-                repositories.extend(repos);
+    fn apply_git_host(&self, mut repositories: Vec<Repository>) -> Vec<Repository> {
+        let Some(git_host) = &self.git_host else {
+            return repositories;
+        };
 
-                if is_last_page {
-                    break;
+        for repo in &mut repositories {
+            if let Ok(mut clone_url) = url::Url::parse(&repo.clone_url) {
+                let _ = clone_url.set_scheme(git_host.scheme());
+                let _ = clone_url.set_host(git_host.host_str());
+                let _ = clone_url.set_port(git_host.port());
+                repo.clone_url = clone_url.to_string();
+            }
+
+            if let (Some(host), Some((user, rest))) =
+                (git_host.host_str(), repo.ssh_url.split_once('@'))
+            {
+                if let Some((_, path)) = rest.split_once(':') {
+                    repo.ssh_url = format!("{user}@{host}:{path}");
                 }
-            } else {
-                let status = response.status();
-                let text = response.text().await.unwrap_or_default();
-                return Err(GhExportError::GitHubApi(format!(
-                    "Failed to list repositories: {status} - {text}"
-                )));
             }
+        }
+
+        repositories
+    }
+
+    async fn get_repository_at(&self, url: &str, name: &str) -> Result<Repository> {
+        let response = self.get_cached(url).await?;
+
+        if response.status == StatusCode::NOT_FOUND {
+            return Err(GhExportError::NotFound(format!(
+                "Repository {name} does not exist"
+            )));
+        }
+
+        if !response.status.is_success() {
+            return Err(GhExportError::GitHubApi(format!(
+                "Failed to get repository {name}: {} - {}",
+                response.status, response.body
+            )));
+        }
+
+        let repository = serde_json::from_str(&response.body)?;
+        Ok(self.apply_git_host(vec![repository]).remove(0))
+    }
+
+    async fn get_paginated<T: DeserializeOwned>(&self, url: &str, action: &str) -> Result<Vec<T>> {
+        self.paginate(url, action).try_collect().await
+    }
+
+    async fn get_cached(&self, url: &str) -> Result<ApiResponse> {
+        // Incremental `since=` queries are unique per run and would never be reused.
+        let cache = self.cache.as_ref().filter(|_| !url.contains("since="));
+        let cached = cache.and_then(|cache| cache.lookup(url));
+
+        let mut request = self.client.get(url);
+        if let Some(entry) = &cached {
+            if let Some(etag) = &entry.etag {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                request = request.header(IF_MODIFIED_SINCE, last_modified);
+            }
+        }
+
+        let response = self.send(request).await?;
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            if let Some(entry) = cached {
+                debug!("Serving {} from cache", url);
+                return Ok(ApiResponse {
+                    status: StatusCode::OK,
+                    link: entry.link,
+                    body: entry.body,
+                });
+            }
+        }
 
-            page += 1;
+        let status = response.status();
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value: &HeaderValue| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let link = header(LINK);
+        let etag = header(ETAG);
+        let last_modified = header(LAST_MODIFIED);
+        let body = response.text().await?;
+
+        if let Some(cache) = cache {
+            if status.is_success() && (etag.is_some() || last_modified.is_some()) {
+                let entry = CachedResponse {
+                    url: url.to_string(),
+                    etag,
+                    last_modified,
+                    link: link.clone(),
+                    body: body.clone(),
+                };
+                if let Err(e) = cache.store(&entry) {
+                    warn!("Failed to cache response for {}: {}", url, e);
+                }
+            }
         }
 
-        Ok(repositories)
+        Ok(ApiResponse { status, link, body })
+    }
+
+    fn paginate<'a, T: DeserializeOwned + 'a>(
+        &'a self,
+        url: &str,
+        action: &str,
+    ) -> impl Stream<Item = Result<T>> + 'a {
+        let separator = if url.contains('?') { '&' } else { '?' };
+        let first_page = format!("{url}{separator}per_page={PER_PAGE}");
+        let action = action.to_string();
+
+        stream::try_unfold(Some(first_page), move |next_page| {
+            let action = action.clone();
+            async move {
+                let Some(page_url) = next_page else {
+                    return Ok(None);
+                };
+
+                debug!("Fetching {}", page_url);
+                let response = self.get_cached(&page_url).await?;
+
+                if response.status == StatusCode::NOT_FOUND {
+                    return Err(GhExportError::NotFound(format!(
+                        "Failed to {action}: {page_url}"
+                    )));
+                }
+
+                if response.status == StatusCode::UNAUTHORIZED {
+                    return Err(GhExportError::Auth(format!(
+                        "Failed to {action}: GitHub rejected the token"
+                    )));
+                }
+
+                if !response.status.is_success() {
+                    return Err(GhExportError::GitHubApi(format!(
+                        "Failed to {action}: {} - {}",
+                        response.status, response.body
+                    )));
+                }
+
+                let next_page = response.link.as_deref().and_then(next_page_url);
+                let items: Vec<T> = serde_json::from_str(&response.body)?;
+                Ok(Some((stream::iter(items.into_iter().map(Ok)), next_page)))
+            }
+        })
+        .try_flatten()
     }
 
-    #[allow(dead_code)]
     pub async fn check_rate_limit(&self) -> Result<RateLimitResponse> {
-        let url = format!("{GITHUB_API_BASE}/rate_limit");
-        let response = self.client.get(&url).send().await?;
+        let url = format!("{}/rate_limit", self.api_base);
+        let response = self.send(self.client.get(&url)).await?;
 
         if !response.status().is_success() {
             let status = response.status();
@@ -183,26 +570,60 @@ impl GitHubClient {
         Ok(response.json().await?)
     }
 
-    #[allow(dead_code)]
-    pub async fn wait_for_rate_limit(&self) -> Result<()> {
-        let rate_limit = self.check_rate_limit().await?;
-
-        if rate_limit.rate.remaining == 0 {
-            let reset_time = chrono::DateTime::from_timestamp(rate_limit.rate.reset as i64, 0)
-                .unwrap_or_else(chrono::Utc::now);
-            let now = chrono::Utc::now();
-
-            if reset_time > now {
-                let wait_duration = reset_time - now;
-                warn!(
-                    "Rate limit exceeded. Waiting {} seconds until reset...",
-                    wait_duration.num_seconds()
-                );
-                tokio::time::sleep(wait_duration.to_std().unwrap_or(Duration::from_secs(60))).await;
-            }
-        }
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let mut attempt = 0;
+        let mut waited = Duration::ZERO;
+
+        loop {
+            let pending = request.try_clone().ok_or_else(|| {
+                GhExportError::GitHubApi("Request body cannot be retried".to_string())
+            })?;
+
+            let delay = match pending.send().await {
+                Ok(response) => match classify_response(&response, attempt) {
+                    Some(delay) => delay,
+                    // Secondary rate limits often come as a plain 403 that only
+                    // says so in its body.
+                    None if response.status() == StatusCode::FORBIDDEN => {
+                        let (response, body) = buffer_response(response).await?;
+                        if !String::from_utf8_lossy(&body)
+                            .to_ascii_lowercase()
+                            .contains("secondary rate limit")
+                        {
+                            return Ok(response);
+                        }
+                        secondary_rate_limit(attempt)
+                    }
+                    None => return Ok(response),
+                },
+                Err(e) if (e.is_timeout() || e.is_connect()) && attempt < MAX_RETRIES => {
+                    warn!("Request failed ({}), retrying...", e);
+                    RetryDelay::Transient(backoff(attempt))
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let wait = match delay {
+                RetryDelay::RateLimited { wait, reset } => {
+                    if waited + wait > self.max_rate_limit_wait {
+                        return Err(GhExportError::RateLimit(reset));
+                    }
+                    warn!(
+                        "Rate limit exceeded. Waiting {} seconds until reset...",
+                        wait.as_secs()
+                    );
+                    waited += wait;
+                    wait
+                }
+                RetryDelay::Transient(wait) => {
+                    debug!("Retrying request in {} seconds", wait.as_secs());
+                    wait
+                }
+            };
 
-        Ok(())
+            tokio::time::sleep(wait).await;
+            attempt += 1;
+        }
     }
 
     #[allow(dead_code)]
@@ -210,3 +631,150 @@ impl GitHubClient {
         &self.token
     }
 }
+
+fn next_page_url(link: &str) -> Option<String> {
+    link.split(',').find_map(|part| {
+        let mut segments = part.split(';');
+        let target = segments.next()?.trim();
+        let is_next = segments.any(|param| {
+            param
+                .trim()
+                .strip_prefix("rel=")
+                .is_some_and(|rel| rel.trim_matches('"').split(' ').any(|r| r == "next"))
+        });
+
+        is_next.then(|| {
+            target
+                .trim_start_matches('<')
+                .trim_end_matches('>')
+                .to_string()
+        })
+    })
+}
+
+fn secondary_rate_limit(attempt: u32) -> RetryDelay {
+    RetryDelay::RateLimited {
+        wait: SECONDARY_RATE_LIMIT_WAIT.max(backoff(attempt)),
+        reset: "unknown (secondary rate limit)".to_string(),
+    }
+}
+
+// Reads the body and hands back an equivalent response for the caller.
+async fn buffer_response(response: reqwest::Response) -> Result<(reqwest::Response, bytes::Bytes)> {
+    let mut builder = http::Response::builder()
+        .status(response.status())
+        .version(response.version());
+    for (name, value) in response.headers() {
+        builder = builder.header(name, value);
+    }
+    let body = response.bytes().await?;
+    let rebuilt = builder
+        .body(body.clone())
+        .map_err(|e| GhExportError::GitHubApi(format!("Invalid response: {e}")))?;
+    Ok((reqwest::Response::from(rebuilt), body))
+}
+
+fn classify_response(response: &reqwest::Response, attempt: u32) -> Option<RetryDelay> {
+    let status = response.status();
+    let header = |name: &str| {
+        response
+            .headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+    };
+
+    if status == StatusCode::FORBIDDEN || status == StatusCode::TOO_MANY_REQUESTS {
+        if let Some(seconds) = header("retry-after") {
+            return Some(RetryDelay::RateLimited {
+                wait: Duration::from_secs(seconds.max(1)),
+                reset: format!("{seconds} seconds (secondary rate limit)"),
+            });
+        }
+
+        if header("x-ratelimit-remaining") == Some(0) {
+            // Without a reset time, wait as long as for a secondary rate limit
+            // rather than retrying right away.
+            let Some(reset_time) = header("x-ratelimit-reset")
+                .and_then(|reset| chrono::DateTime::from_timestamp(reset as i64, 0))
+            else {
+                return Some(RetryDelay::RateLimited {
+                    wait: SECONDARY_RATE_LIMIT_WAIT.max(backoff(attempt)),
+                    reset: "unknown".to_string(),
+                });
+            };
+            let wait = (reset_time - chrono::Utc::now())
+                .to_std()
+                .unwrap_or(Duration::ZERO)
+                + Duration::from_secs(1);
+            return Some(RetryDelay::RateLimited {
+                wait,
+                reset: reset_time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+            });
+        }
+
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Some(secondary_rate_limit(attempt));
+        }
+    }
+
+    if status.is_server_error() && attempt < MAX_RETRIES {
+        warn!("Server returned {}, retrying...", status);
+        return Some(RetryDelay::Transient(backoff(attempt)));
+    }
+
+    None
+}
+
+fn backoff(attempt: u32) -> Duration {
+    Duration::from_secs(1 << attempt.min(6)).min(MAX_BACKOFF)
+}
+
+fn with_since(url: String, since: Option<&str>) -> String {
+    match since {
+        Some(since) => {
+            let separator = if url.contains('?') { '&' } else { '?' };
+            format!("{url}{separator}since={since}")
+        }
+        None => url,
+    }
+}
+
+pub fn normalize_api_base(api_base: &str) -> Result<String> {
+    let mut url = url::Url::parse(api_base.trim())
+        .map_err(|e| GhExportError::InvalidUrl(format!("{api_base}: {e}")))?;
+
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(GhExportError::InvalidUrl(format!(
+            "{api_base}: API base must use http or https"
+        )));
+    }
+
+    // GitHub Enterprise Server serves its REST API under /api/v3 on the
+    // instance host; local mock servers are left untouched.
+    let needs_api_path = match url.host() {
+        Some(url::Host::Domain(domain)) => domain != "api.github.com" && domain != "localhost",
+        Some(url::Host::Ipv4(ip)) => !ip.is_loopback(),
+        Some(url::Host::Ipv6(ip)) => !ip.is_loopback(),
+        None => false,
+    };
+    if needs_api_path && url.path().trim_end_matches('/').is_empty() {
+        url.set_path("/api/v3");
+    }
+
+    Ok(url.as_str().trim_end_matches('/').to_string())
+}
+
+fn parse_git_host(git_host: &str) -> Result<url::Url> {
+    let git_host = git_host.trim();
+    let candidate = if git_host.contains("://") {
+        git_host.to_string()
+    } else {
+        format!("https://{git_host}")
+    };
+
+    url::Url::parse(&candidate)
+        .ok()
+        .filter(|url| url.host_str().is_some())
+        .ok_or_else(|| GhExportError::InvalidUrl(format!("{git_host}: invalid git host")))
+}
@@ -0,0 +1,126 @@
+use crate::download::export_data_dir;
+use crate::error::Result;
+use crate::github::{GitHubClient, Repository};
+use futures::{future, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use tracing::debug;
+
+const SYNC_STATE_FILE: &str = "sync.json";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IssueSyncState {
+    last_sync: chrono::DateTime<chrono::Utc>,
+}
+
+pub async fn export_issues(
+    client: &GitHubClient,
+    repo: &Repository,
+    repo_path: &Path,
+) -> Result<()> {
+    let issues_dir = export_data_dir(repo_path)?.join("issues");
+    fs::create_dir_all(&issues_dir)?;
+
+    let state_path = issues_dir.join(SYNC_STATE_FILE);
+    let previous_sync = load_sync_state(&state_path)?.map(|state| state.last_sync);
+    let since = previous_sync.map(|time| time.to_rfc3339_opts(chrono::SecondsFormat::Secs, true));
+    let started_at = chrono::Utc::now();
+
+    debug!(
+        "Exporting issues for {} (since: {})",
+        repo.full_name,
+        since.as_deref().unwrap_or("beginning")
+    );
+
+    // The issues endpoint also returns pull requests, so this collects every
+    // issue and pull request that changed since the last export.
+    let mut updated = BTreeSet::new();
+
+    if repo.has_issues {
+        let issues = client
+            .list_issues(&repo.full_name, since.as_deref())
+            .await?;
+        write_items(&issues_dir.join("issues"), &issues, "number")?;
+        updated.extend(issues.iter().filter_map(|issue| issue["number"].as_u64()));
+
+        let comments = client
+            .list_issue_comments(&repo.full_name, since.as_deref())
+            .await?;
+        write_items(&issues_dir.join("comments"), &comments, "id")?;
+    }
+
+    let pulls: Vec<_> = client
+        .list_pull_requests(&repo.full_name)
+        .try_take_while(|pull| future::ready(Ok(is_updated_since(pull, previous_sync))))
+        .try_collect()
+        .await?;
+    write_items(&issues_dir.join("pulls"), &pulls, "number")?;
+    updated.extend(pulls.iter().filter_map(|pull| pull["number"].as_u64()));
+
+    let review_comments = client
+        .list_review_comments(&repo.full_name, since.as_deref())
+        .await?;
+    write_items(&issues_dir.join("review_comments"), &review_comments, "id")?;
+
+    let timelines_dir = issues_dir.join("timelines");
+    for number in updated {
+        let timeline = client.list_timeline(&repo.full_name, number).await?;
+        fs::create_dir_all(&timelines_dir)?;
+        fs::write(
+            timelines_dir.join(format!("{number}.json")),
+            serde_json::to_string_pretty(&timeline)?,
+        )?;
+    }
+
+    let state = IssueSyncState {
+        last_sync: started_at,
+    };
+    fs::write(&state_path, serde_json::to_string_pretty(&state)?)?;
+
+    Ok(())
+}
+
+fn load_sync_state(path: &Path) -> Result<Option<IssueSyncState>> {
+    if path.exists() {
+        let content = fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&content)?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn is_updated_since(
+    item: &serde_json::Value,
+    since: Option<chrono::DateTime<chrono::Utc>>,
+) -> bool {
+    let Some(since) = since else {
+        return true;
+    };
+
+    item.get("updated_at")
+        .and_then(|value| value.as_str())
+        .and_then(|value| chrono::DateTime::parse_from_rfc3339(value).ok())
+        .is_none_or(|updated_at| updated_at >= since)
+}
+
+fn write_items(dir: &Path, items: &[serde_json::Value], key: &str) -> Result<()> {
+    if items.is_empty() {
+        return Ok(());
+    }
+
+    fs::create_dir_all(dir)?;
+
+    for item in items {
+        let Some(id) = item.get(key).and_then(|value| value.as_u64()) else {
+            continue;
+        };
+        fs::write(
+            dir.join(format!("{id}.json")),
+            serde_json::to_string_pretty(item)?,
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use crate::error::Result;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{debug, warn};
+
+const JOURNAL_FILE: &str = ".gh-export-journal.jsonl";
+const MAX_RESUME_AGE: chrono::Duration = chrono::Duration::hours(24);
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum JournalEntry {
+    Started {
+        at: chrono::DateTime<chrono::Utc>,
+        #[serde(default)]
+        fingerprint: String,
+    },
+    Completed {
+        repo: String,
+    },
+}
+
+// Each finished repository is appended and synced straight away, so a run that
+// is killed outright still leaves a journal to resume from. The file is only
+// created once the first repository finishes and is removed by `finish`, so a
+// run that gets that far never leaves a stale journal behind.
+//
+// A journal is only resumed by a run that exports the same selection with the
+// same options, within a day, and when no export has finished since it was
+// started. Anything else starts over and replaces it.
+pub struct Journal {
+    path: PathBuf,
+    completed: HashSet<String>,
+    resumed_from: Option<chrono::DateTime<chrono::Utc>>,
+    started_at: chrono::DateTime<chrono::Utc>,
+    fingerprint: String,
+    file: Mutex<Option<File>>,
+}
+
+impl Journal {
+    pub fn open(
+        output_dir: &Path,
+        started_at: chrono::DateTime<chrono::Utc>,
+        fingerprint: String,
+        last_finished: Option<chrono::DateTime<chrono::Utc>>,
+    ) -> Result<Self> {
+        let path = output_dir.join(JOURNAL_FILE);
+        let mut completed = HashSet::new();
+        let mut resumed_from = None;
+        let mut matches = false;
+
+        if path.exists() {
+            for line in BufReader::new(File::open(&path)?).lines() {
+                match serde_json::from_str(&line?) {
+                    Ok(JournalEntry::Started { at, fingerprint: f }) => {
+                        if resumed_from.is_none() {
+                            resumed_from = Some(at);
+                            matches = f == fingerprint
+                                && started_at - at <= MAX_RESUME_AGE
+                                && last_finished.is_none_or(|finished| finished < at);
+                        }
+                    }
+                    Ok(JournalEntry::Completed { repo }) => {
+                        completed.insert(repo);
+                    }
+                    Err(e) => warn!("Ignoring unreadable journal entry: {}", e),
+                }
+            }
+        }
+
+        if !matches && (resumed_from.is_some() || !completed.is_empty()) {
+            debug!("Not resuming from a journal left by a different or finished export");
+            completed.clear();
+            resumed_from = None;
+        }
+
+        Ok(Self {
+            path,
+            completed,
+            resumed_from,
+            started_at,
+            fingerprint,
+            file: Mutex::new(None),
+        })
+    }
+
+    pub fn resumed_from(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.resumed_from
+    }
+
+    pub fn is_completed(&self, full_name: &str) -> bool {
+        self.completed.contains(full_name)
+    }
+
+    pub fn record_completed(&self, full_name: &str) -> Result<()> {
+        let mut file = self.file.lock().unwrap();
+        if file.is_none() {
+            let resuming = self.resumed_from.is_some();
+            let mut opened = OpenOptions::new()
+                .create(true)
+                .append(resuming)
+                .write(true)
+                .truncate(!resuming)
+                .open(&self.path)?;
+            if !resuming {
+                append(
+                    &mut opened,
+                    &JournalEntry::Started {
+                        at: self.started_at,
+                        fingerprint: self.fingerprint.clone(),
+                    },
+                )?;
+            }
+            *file = Some(opened);
+        }
+
+        append(
+            file.as_mut().unwrap(),
+            &JournalEntry::Completed {
+                repo: full_name.to_string(),
+            },
+        )
+    }
+
+    pub fn finish(&self) -> Result<()> {
+        self.file.lock().unwrap().take();
+        if self.path.exists() {
+            fs::remove_file(&self.path)?;
+        }
+        Ok(())
+    }
+}
+
+// Identifies what a run exports, so that a journal is only resumed by a run
+// that would have exported the same repositories in the same way.
+pub fn fingerprint<'a>(repositories: impl IntoIterator<Item = &'a str>, options: &str) -> String {
+    let mut names: Vec<_> = repositories.into_iter().collect();
+    names.sort_unstable();
+
+    let mut hasher = Sha256::new();
+    for name in names {
+        hasher.update(name.as_bytes());
+        hasher.update(b"\n");
+    }
+    hasher.update(options.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+fn append(file: &mut File, entry: &JournalEntry) -> Result<()> {
+    let mut line = serde_json::to_string(entry)?;
+    line.push('\n');
+    file.write_all(line.as_bytes())?;
+    file.sync_data()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn started(hours_ago: i64) -> chrono::DateTime<chrono::Utc> {
+        chrono::Utc::now() - chrono::Duration::hours(hours_ago)
+    }
+
+    // Leaves a journal behind as a run started `hours_ago` would have.
+    fn interrupted_run(dir: &Path, hours_ago: i64) {
+        let journal =
+            Journal::open(dir, started(hours_ago), "selection".to_string(), None).unwrap();
+        journal.record_completed("octocat/done").unwrap();
+    }
+
+    #[test]
+    fn resumes_a_recent_journal_of_the_same_selection() {
+        let dir = tempfile::tempdir().unwrap();
+        interrupted_run(dir.path(), 1);
+
+        let journal = Journal::open(dir.path(), started(0), "selection".to_string(), None).unwrap();
+        assert!(journal.resumed_from().is_some());
+        assert!(journal.is_completed("octocat/done"));
+    }
+
+    #[test]
+    fn ignores_journals_that_do_not_match_the_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let open = |fingerprint: &str, finished| {
+            Journal::open(dir.path(), started(0), fingerprint.to_string(), finished).unwrap()
+        };
+
+        interrupted_run(dir.path(), 1);
+        assert!(!open("other", None).is_completed("octocat/done"));
+        assert!(!open("selection", Some(started(0))).is_completed("octocat/done"));
+
+        let old = tempfile::tempdir().unwrap();
+        interrupted_run(old.path(), 25);
+        let journal = Journal::open(old.path(), started(0), "selection".to_string(), None).unwrap();
+        assert!(!journal.is_completed("octocat/done"));
+    }
+
+    #[test]
+    fn a_new_run_replaces_an_ignored_journal() {
+        let dir = tempfile::tempdir().unwrap();
+        interrupted_run(dir.path(), 25);
+
+        let journal = Journal::open(dir.path(), started(0), "selection".to_string(), None).unwrap();
+        journal.record_completed("octocat/other").unwrap();
+
+        let reopened =
+            Journal::open(dir.path(), started(0), "selection".to_string(), None).unwrap();
+        assert!(reopened.is_completed("octocat/other"));
+        assert!(!reopened.is_completed("octocat/done"));
+    }
+}
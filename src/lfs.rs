@@ -0,0 +1,548 @@
+use crate::error::{GhExportError, Result};
+use crate::github::GitHubClient;
+use futures::StreamExt;
+use git2::{ObjectType, Oid};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, warn};
+
+const LFS_MEDIA_TYPE: &str = "application/vnd.git-lfs+json";
+const LFS_POINTER_VERSION: &str = "version https://git-lfs.github.com/spec/v1";
+const MAX_POINTER_SIZE: usize = 1024;
+const BATCH_SIZE: usize = 100;
+const OBJECT_DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+struct LfsPointer {
+    oid: String,
+    size: u64,
+}
+
+#[derive(Serialize)]
+struct BatchRequest<'a> {
+    operation: &'static str,
+    transfers: [&'static str; 1],
+    objects: &'a [LfsPointer],
+}
+
+#[derive(Deserialize)]
+struct BatchResponse {
+    objects: Vec<BatchObject>,
+}
+
+#[derive(Deserialize)]
+struct BatchObject {
+    oid: String,
+    size: u64,
+    #[serde(default)]
+    actions: Option<BatchActions>,
+    #[serde(default)]
+    error: Option<BatchError>,
+}
+
+#[derive(Deserialize)]
+struct BatchActions {
+    download: Option<BatchAction>,
+}
+
+#[derive(Deserialize)]
+struct BatchAction {
+    href: String,
+    #[serde(default)]
+    header: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct BatchError {
+    code: u16,
+    message: String,
+}
+
+#[derive(Clone)]
+pub struct LfsClient {
+    client: GitHubClient,
+}
+
+impl LfsClient {
+    pub fn new(client: GitHubClient) -> Self {
+        Self { client }
+    }
+
+    pub async fn fetch_objects(
+        &self,
+        clone_url: &str,
+        repo_path: &Path,
+        all_history: bool,
+    ) -> Result<usize> {
+        let repo_path = repo_path.to_path_buf();
+        let (objects_dir, pointers) =
+            tokio::task::spawn_blocking(move || find_missing_pointers(&repo_path, all_history))
+                .await
+                .map_err(|e| GhExportError::Download(format!("LFS scan task failed: {e}")))??;
+
+        if pointers.is_empty() {
+            return Ok(0);
+        }
+
+        debug!(
+            "Downloading {} LFS objects for {}",
+            pointers.len(),
+            clone_url
+        );
+        let batch_url = format!(
+            "{}.git/info/lfs/objects/batch",
+            clone_url.strip_suffix(".git").unwrap_or(clone_url)
+        );
+
+        let mut downloaded = 0;
+        let mut unavailable = Vec::new();
+        let mut missing_upstream = true;
+
+        for chunk in pointers.chunks(BATCH_SIZE) {
+            for object in self.request_batch(&batch_url, chunk).await? {
+                if let Some(error) = object.error {
+                    warn!(
+                        "LFS object {} unavailable ({}): {}",
+                        object.oid, error.code, error.message
+                    );
+                    // 404 and 410 mean the server no longer has the object;
+                    // anything else may succeed on a later attempt.
+                    missing_upstream &= matches!(error.code, 404 | 410);
+                    unavailable.push(object.oid);
+                    continue;
+                }
+
+                if !is_valid_oid(&object.oid) {
+                    warn!("LFS server returned an invalid oid {}", object.oid);
+                    unavailable.push(object.oid);
+                    continue;
+                }
+
+                // Without a download action the object can't be fetched, even
+                // though the server didn't report an error for it.
+                let Some(action) = object.actions.and_then(|actions| actions.download) else {
+                    warn!("LFS server returned no download action for {}", object.oid);
+                    missing_upstream = false;
+                    unavailable.push(object.oid);
+                    continue;
+                };
+
+                let pointer = LfsPointer {
+                    oid: object.oid,
+                    size: object.size,
+                };
+                self.download_object(&action, &pointer, &objects_dir)
+                    .await?;
+                downloaded += 1;
+            }
+        }
+
+        if !unavailable.is_empty() {
+            let message = format!(
+                "{} of {} LFS objects could not be downloaded ({} downloaded): {}",
+                unavailable.len(),
+                pointers.len(),
+                downloaded,
+                unavailable.join(", ")
+            );
+            return Err(if missing_upstream {
+                GhExportError::Corrupt(message)
+            } else {
+                GhExportError::Transfer(message)
+            });
+        }
+
+        Ok(downloaded)
+    }
+
+    async fn request_batch(&self, url: &str, objects: &[LfsPointer]) -> Result<Vec<BatchObject>> {
+        let response = self
+            .client
+            .post_lfs_batch(
+                url,
+                LFS_MEDIA_TYPE,
+                &BatchRequest {
+                    operation: "download",
+                    transfers: ["basic"],
+                    objects,
+                },
+            )
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            return Err(GhExportError::Download(format!(
+                "LFS batch request failed: {status} - {text}"
+            )));
+        }
+
+        let batch: BatchResponse = response.json().await?;
+        Ok(batch.objects)
+    }
+
+    async fn download_object(
+        &self,
+        action: &BatchAction,
+        pointer: &LfsPointer,
+        objects_dir: &Path,
+    ) -> Result<()> {
+        let target = object_path(objects_dir, &pointer.oid);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let partial_path = target.with_extension("part");
+
+        let response = self
+            .client
+            .download(&action.href, &action.header, OBJECT_DOWNLOAD_TIMEOUT)
+            .await?;
+        if !response.status().is_success() {
+            return Err(GhExportError::Transfer(format!(
+                "Failed to download LFS object {}: {}",
+                pointer.oid,
+                response.status()
+            )));
+        }
+
+        let mut stream = response.bytes_stream();
+        let mut file = tokio::fs::File::create(&partial_path).await?;
+        let mut hasher = Sha256::new();
+        let mut written = 0u64;
+
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            hasher.update(&chunk);
+            written += chunk.len() as u64;
+            file.write_all(&chunk).await?;
+        }
+
+        file.flush().await?;
+        drop(file);
+
+        let actual = format!("{:x}", hasher.finalize());
+        if written != pointer.size || actual != pointer.oid {
+            let _ = fs::remove_file(&partial_path);
+            return Err(GhExportError::Corrupt(format!(
+                "LFS object {} failed verification (got {written} bytes, sha256 {actual})",
+                pointer.oid
+            )));
+        }
+
+        fs::rename(&partial_path, &target)?;
+        Ok(())
+    }
+}
+
+fn object_path(objects_dir: &Path, oid: &str) -> PathBuf {
+    objects_dir.join(&oid[0..2]).join(&oid[2..4]).join(oid)
+}
+
+fn find_missing_pointers(
+    repo_path: &Path,
+    all_history: bool,
+) -> Result<(PathBuf, Vec<LfsPointer>)> {
+    let repo = git2::Repository::open(repo_path)?;
+    let objects_dir = repo.path().join("lfs").join("objects");
+
+    let mut commits = Vec::new();
+    for reference in repo.references()? {
+        if let Ok(commit) = reference?.peel_to_commit() {
+            commits.push(commit.id());
+        }
+    }
+
+    if all_history {
+        let mut revwalk = repo.revwalk()?;
+        for commit in &commits {
+            revwalk.push(*commit)?;
+        }
+        commits = revwalk.collect::<std::result::Result<Vec<_>, _>>()?;
+    }
+
+    commits.sort();
+    commits.dedup();
+
+    let mut visited = HashSet::new();
+    let mut uses_lfs = false;
+    for commit in &commits {
+        let tree_id = repo.find_commit(*commit)?.tree_id();
+        if has_lfs_attributes(&repo, tree_id, &mut visited)? {
+            uses_lfs = true;
+            break;
+        }
+    }
+    if !uses_lfs {
+        return Ok((objects_dir, Vec::new()));
+    }
+
+    let odb = repo.odb()?;
+    visited.clear();
+    let mut pointers = HashSet::new();
+
+    for commit in commits {
+        let tree = repo.find_commit(commit)?.tree()?;
+        collect_pointers(&repo, &odb, tree.id(), &mut visited, &mut pointers)?;
+    }
+
+    let missing = pointers
+        .into_iter()
+        .filter(|pointer| !is_stored(&objects_dir, pointer))
+        .collect();
+
+    Ok((objects_dir, missing))
+}
+
+// An object only counts as stored when its content matches the pointer, the
+// same check `download_object` makes for new downloads.
+fn is_stored(objects_dir: &Path, pointer: &LfsPointer) -> bool {
+    let path = object_path(objects_dir, &pointer.oid);
+    if fs::metadata(&path).map_or(true, |metadata| metadata.len() != pointer.size) {
+        return false;
+    }
+
+    let mut hasher = Sha256::new();
+    fs::File::open(&path)
+        .and_then(|mut file| std::io::copy(&mut file, &mut hasher))
+        .is_ok_and(|_| format!("{:x}", hasher.finalize()) == pointer.oid)
+}
+
+// `.gitattributes` files can live in any directory, so every one in the tree
+// is checked. Trees already seen in another commit are skipped.
+fn has_lfs_attributes(
+    repo: &git2::Repository,
+    tree_id: Oid,
+    visited: &mut HashSet<Oid>,
+) -> Result<bool> {
+    if !visited.insert(tree_id) {
+        return Ok(false);
+    }
+
+    let tree = repo.find_tree(tree_id)?;
+    for entry in tree.iter() {
+        let found = match entry.kind() {
+            Some(ObjectType::Tree) => has_lfs_attributes(repo, entry.id(), visited)?,
+            Some(ObjectType::Blob) if entry.name() == Some(".gitattributes") => {
+                String::from_utf8_lossy(repo.find_blob(entry.id())?.content())
+                    .lines()
+                    .any(|line| line.contains("filter=lfs"))
+            }
+            _ => false,
+        };
+        if found {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+fn collect_pointers(
+    repo: &git2::Repository,
+    odb: &git2::Odb,
+    tree_id: Oid,
+    visited: &mut HashSet<Oid>,
+    pointers: &mut HashSet<LfsPointer>,
+) -> Result<()> {
+    if !visited.insert(tree_id) {
+        return Ok(());
+    }
+
+    let tree = repo.find_tree(tree_id)?;
+    for entry in tree.iter() {
+        match entry.kind() {
+            Some(ObjectType::Tree) => {
+                collect_pointers(repo, odb, entry.id(), visited, pointers)?;
+            }
+            Some(ObjectType::Blob) if visited.insert(entry.id()) => {
+                let (size, _) = odb.read_header(entry.id())?;
+                if size > MAX_POINTER_SIZE {
+                    continue;
+                }
+                let blob = repo.find_blob(entry.id())?;
+                if let Some(pointer) = parse_pointer(blob.content()) {
+                    pointers.insert(pointer);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_pointer(content: &[u8]) -> Option<LfsPointer> {
+    let text = std::str::from_utf8(content).ok()?;
+    let mut lines = text.lines();
+    if lines.next()? != LFS_POINTER_VERSION {
+        return None;
+    }
+
+    let mut oid = None;
+    let mut size = None;
+    for line in lines {
+        if let Some(value) = line.strip_prefix("oid sha256:") {
+            oid = Some(value.to_string());
+        } else if let Some(value) = line.strip_prefix("size ") {
+            size = value.parse().ok();
+        }
+    }
+
+    let oid = oid
+        .map(|oid| oid.to_ascii_lowercase())
+        .filter(|oid| is_valid_oid(oid))?;
+    Some(LfsPointer { oid, size: size? })
+}
+
+fn is_valid_oid(oid: &str) -> bool {
+    oid.len() == 64
+        && oid
+            .chars()
+            .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONTENT: &[u8] = b"design asset";
+
+    // A repository whose only LFS rule lives in a nested `.gitattributes`.
+    fn repository_with_pointer() -> (tempfile::TempDir, String) {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = git2::Repository::init(dir.path()).unwrap();
+        let oid = format!("{:x}", Sha256::digest(CONTENT));
+        let pointer = format!(
+            "{LFS_POINTER_VERSION}\noid sha256:{oid}\nsize {}\n",
+            CONTENT.len()
+        );
+
+        let attributes = repo
+            .blob(b"*.psd filter=lfs diff=lfs merge=lfs -text\n")
+            .unwrap();
+        let pointer = repo.blob(pointer.as_bytes()).unwrap();
+        let mut assets = repo.treebuilder(None).unwrap();
+        assets
+            .insert(".gitattributes", attributes, 0o100644)
+            .unwrap();
+        assets.insert("logo.psd", pointer, 0o100644).unwrap();
+        let assets = assets.write().unwrap();
+        let mut root = repo.treebuilder(None).unwrap();
+        root.insert("assets", assets, 0o040000).unwrap();
+        let tree = repo.find_tree(root.write().unwrap()).unwrap();
+
+        let signature = git2::Signature::now("Octocat", "octocat@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "Add logo", &tree, &[])
+            .unwrap();
+        (dir, oid)
+    }
+
+    async fn fetch_from(server: &mut mockito::ServerGuard, served: &[u8]) -> (Result<usize>, bool) {
+        fetch_with(server, served, None, true).await
+    }
+
+    // Fetches the pointer's object, optionally with a file already stored in
+    // its place and with the batch response leaving out the download action.
+    async fn fetch_with(
+        server: &mut mockito::ServerGuard,
+        served: &[u8],
+        existing: Option<&[u8]>,
+        with_action: bool,
+    ) -> (Result<usize>, bool) {
+        let (dir, oid) = repository_with_pointer();
+        if let Some(existing) = existing {
+            let path = object_path(&dir.path().join(".git/lfs/objects"), &oid);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, existing).unwrap();
+        }
+        let actions = if with_action {
+            format!(
+                r#","actions":{{"download":{{"href":"{}/objects/{oid}"}}}}"#,
+                server.url()
+            )
+        } else {
+            String::new()
+        };
+        let _batch = server
+            .mock("POST", "/acme/assets.git/info/lfs/objects/batch")
+            .match_header(
+                "authorization",
+                mockito::Matcher::Regex("^Basic ".to_string()),
+            )
+            .with_status(200)
+            .with_body(format!(
+                r#"{{"objects":[{{"oid":"{oid}","size":{}{actions}}}]}}"#,
+                CONTENT.len(),
+            ))
+            .create_async()
+            .await;
+        let _object = server
+            .mock("GET", format!("/objects/{oid}").as_str())
+            .match_header("authorization", mockito::Matcher::Missing)
+            .with_status(200)
+            .with_body(served)
+            .create_async()
+            .await;
+
+        let client = GitHubClient::new(
+            "token".to_string(),
+            &server.url(),
+            None,
+            Duration::ZERO,
+            None,
+        )
+        .unwrap();
+        let result = LfsClient::new(client)
+            .fetch_objects(
+                &format!("{}/acme/assets.git", server.url()),
+                dir.path(),
+                false,
+            )
+            .await;
+        let stored = object_path(&dir.path().join(".git/lfs/objects"), &oid).exists();
+        (result, stored)
+    }
+
+    #[tokio::test]
+    async fn downloads_objects_from_batch_api() {
+        let mut server = mockito::Server::new_async().await;
+        let (result, stored) = fetch_from(&mut server, CONTENT).await;
+
+        assert_eq!(result.unwrap(), 1);
+        assert!(stored);
+    }
+
+    #[tokio::test]
+    async fn rejects_objects_with_wrong_sha256() {
+        let mut server = mockito::Server::new_async().await;
+        let (result, stored) = fetch_from(&mut server, b"design assex").await;
+
+        assert!(matches!(result, Err(GhExportError::Corrupt(_))));
+        assert!(!stored);
+    }
+
+    #[tokio::test]
+    async fn objects_without_a_download_action_are_unavailable() {
+        let mut server = mockito::Server::new_async().await;
+        let (result, stored) = fetch_with(&mut server, CONTENT, None, false).await;
+
+        assert!(matches!(result, Err(GhExportError::Transfer(_))));
+        assert!(!stored);
+    }
+
+    #[tokio::test]
+    async fn replaces_stored_objects_with_wrong_content() {
+        let mut server = mockito::Server::new_async().await;
+        let (result, stored) = fetch_with(&mut server, CONTENT, Some(b"design assex"), true).await;
+        assert_eq!(result.unwrap(), 1);
+        assert!(stored);
+
+        let (result, _) = fetch_with(&mut server, CONTENT, Some(CONTENT), true).await;
+        assert_eq!(result.unwrap(), 0);
+    }
+}
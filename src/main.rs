@@ -1,23 +1,52 @@
+mod cache;
 mod cli;
 mod config;
+mod credentials;
 mod download;
 mod error;
+mod events;
+mod filter;
 mod github;
+mod issues;
+mod journal;
+mod lfs;
+mod manifest;
+mod plan;
 mod progress;
+mod releases;
+mod shutdown;
+mod verify;
 
+use cache::HttpCache;
 use clap::Parser;
-use cli::{Cli, Commands, ConfigAction};
-use config::{Config, ExportMetadata};
+use cli::{Cli, Commands, ConfigAction, ListFormat, OutputFormat};
+use config::{Config, ExportMetadata, Protocol};
 use console::style;
+use credentials::{ssh_key_is_encrypted, GitCredentials};
 use dialoguer::{theme::ColorfulTheme, Confirm, Input, Password};
-use download::{check_disk_space, DownloadResult, Downloader};
-use error::Result;
-use github::GitHubClient;
-use progress::{create_spinner, ProgressTracker};
-use std::time::Instant;
+use download::{
+    check_disk_space, cleanup_staging_dirs, DownloadOptions, DownloadResult, Downloader, WikiStatus,
+};
+use error::{FailureCause, Result};
+use events::Event;
+use filter::RepoFilter;
+use futures::StreamExt;
+use github::{GitHubClient, Repository};
+use journal::Journal;
+use manifest::{Manifest, ORPHANED_DIR};
+use plan::{PlannedAction, PlannedRepository};
+use progress::{create_spinner, ProgressMode, ProgressTracker};
+use shutdown::Shutdown;
+use std::collections::HashSet;
+use std::io::IsTerminal;
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tracing::debug;
+use verify::{find_local_repositories, verify_repository};
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
     let mut cli = Cli::parse();
 
     let log_level = if cli.verbose {
@@ -28,17 +57,46 @@ async fn main() -> Result<()> {
         "info"
     };
 
-    tracing_subscriber::fmt().with_env_filter(log_level).init();
+    // JSON events own stdout, so logs go to stderr.
+    if cli.output_format == OutputFormat::Json {
+        tracing_subscriber::fmt()
+            .with_env_filter(log_level)
+            .with_writer(std::io::stderr)
+            .init();
+    } else {
+        tracing_subscriber::fmt().with_env_filter(log_level).init();
+    }
 
-    match cli.command.take() {
-        Some(Commands::Config { action }) => handle_config_command(action).await,
-        Some(Commands::Status) => handle_status_command().await,
-        Some(Commands::Sync { since }) => handle_sync_command(cli, since).await,
+    let result = match cli.command.take() {
+        Some(
+            Commands::Config { .. }
+            | Commands::Status
+            | Commands::Verify { .. }
+            | Commands::List { .. },
+        ) if cli.dry_run => Err(error::GhExportError::Config(
+            "--dry-run only applies to export, sync and retry-failed".to_string(),
+        )),
+        Some(Commands::Config { action }) => {
+            handle_config_command(action, RunOptions::from_cli(&cli)).await
+        }
+        Some(Commands::Status) => handle_status_command(cli).await,
+        Some(Commands::Sync { since, full }) => handle_sync_command(cli, since, full).await,
+        Some(Commands::Verify { offline }) => handle_verify_command(cli, offline).await,
+        Some(Commands::List { format }) => handle_list_command(cli, format).await,
+        Some(Commands::RetryFailed) => handle_retry_failed_command(cli).await,
         None => handle_export_command(cli).await,
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{} {e}", style("Error:").red().bold());
+            ExitCode::from(e.exit_code())
+        }
     }
 }
 
-async fn handle_config_command(action: ConfigAction) -> Result<()> {
+async fn handle_config_command(action: ConfigAction, options: RunOptions) -> Result<()> {
     match action {
         ConfigAction::Show => {
             let config = Config::load()?;
@@ -51,11 +109,48 @@ async fn handle_config_command(action: ConfigAction) -> Result<()> {
                     "Not set"
                 }
             );
+            println!("  API URL: {}", config.api_url);
+            if let Some(git_host) = &config.git_host {
+                println!("  Git host: {git_host}");
+            }
             println!("  Output directory: {}", config.output_directory.display());
             println!("  Parallel downloads: {}", config.parallel_downloads);
             println!("  Include archived: {}", config.include_archived);
             println!("  Exclude forks: {}", config.exclude_forks);
             println!("  Shallow clone: {}", config.shallow_clone);
+            println!("  Mirror mode: {}", config.mirror);
+            println!("  Git LFS: {}", config.lfs);
+            println!("  Recurse submodules: {}", config.recurse_submodules);
+            println!("  Protocol: {:?}", config.protocol);
+            if let Some(ssh_key) = &config.ssh_key {
+                println!("  SSH key: {}", ssh_key.display());
+            }
+            println!("  Export issues: {}", config.export_issues);
+            println!("  Export releases: {}", config.export_releases);
+            println!("  Export wikis: {}", config.export_wikis);
+            println!(
+                "  Organizations: {}",
+                if config.all_organizations {
+                    "all".to_string()
+                } else if config.organizations.is_empty() {
+                    "none".to_string()
+                } else {
+                    config.organizations.join(", ")
+                }
+            );
+            if !config.include.is_empty() {
+                println!("  Include: {}", config.include.join(", "));
+            }
+            if !config.exclude.is_empty() {
+                println!("  Exclude: {}", config.exclude.join(", "));
+            }
+            if !config.languages.is_empty() {
+                println!("  Languages: {}", config.languages.join(", "));
+            }
+            if !config.topics.is_empty() {
+                println!("  Topics: {}", config.topics.join(", "));
+            }
+            println!("  Visibility: {:?}", config.visibility);
             Ok(())
         }
         ConfigAction::Set { key, value } => {
@@ -64,6 +159,20 @@ async fn handle_config_command(action: ConfigAction) -> Result<()> {
             match key.as_str() {
                 "token" => config.github_token = Some(value),
                 "output" => config.output_directory = value.into(),
+                "api_url" => config.api_url = value,
+                "git_host" => config.git_host = Some(value).filter(|host| !host.is_empty()),
+                "orgs" => {
+                    config.organizations = value
+                        .split(',')
+                        .map(|org| org.trim().to_string())
+                        .filter(|org| !org.is_empty())
+                        .collect();
+                }
+                "all_orgs" => {
+                    config.all_organizations = value.parse().map_err(|_| {
+                        error::GhExportError::Config("Invalid all_orgs value".to_string())
+                    })?;
+                }
                 "parallel" => {
                     config.parallel_downloads = value.parse().map_err(|_| {
                         error::GhExportError::Config("Invalid parallel value".to_string())
@@ -81,10 +190,18 @@ async fn handle_config_command(action: ConfigAction) -> Result<()> {
             Ok(())
         }
         ConfigAction::Clear => {
-            if Confirm::with_theme(&ColorfulTheme::default())
-                .with_prompt("Are you sure you want to clear all configuration?")
-                .default(false)
-                .interact()?
+            let prompt = "Are you sure you want to clear all configuration?";
+            if !options.assume_yes && !options.interactive {
+                return Err(error::GhExportError::Config(format!(
+                    "{prompt} Pass --yes to confirm without a terminal."
+                )));
+            }
+
+            if options.assume_yes
+                || Confirm::with_theme(&ColorfulTheme::default())
+                    .with_prompt(prompt)
+                    .default(false)
+                    .interact()?
             {
                 std::fs::remove_file(Config::config_path()?)?;
                 println!("{}", style("Configuration cleared").green());
@@ -94,10 +211,26 @@ async fn handle_config_command(action: ConfigAction) -> Result<()> {
     }
 }
 
-async fn handle_status_command() -> Result<()> {
-    let config = Config::load()?;
+async fn handle_status_command(cli: Cli) -> Result<()> {
+    let mut config = Config::load()?;
+    merge_cli_config(&mut config, &cli);
 
-    if let Some(metadata) = ExportMetadata::load(&config.output_directory)? {
+    if cli.output_format == OutputFormat::Json {
+        let status = match ExportMetadata::find_latest(&config.output_directory)? {
+            Some(metadata) => {
+                let mut status = serde_json::to_value(&metadata)?;
+                status["exported"] = true.into();
+                status
+            }
+            None => serde_json::json!({ "exported": false }),
+        };
+        println!("{}", serde_json::to_string_pretty(&status)?);
+        return Ok(());
+    }
+
+    let manifest = Manifest::load(&config.output_directory)?;
+
+    if let Some(metadata) = ExportMetadata::find_latest(&config.output_directory)? {
         println!("{}", style("Last export information:").bold());
         println!(
             "  Date: {}",
@@ -106,8 +239,14 @@ async fn handle_status_command() -> Result<()> {
         println!("  Total repositories: {}", metadata.total_repos);
         println!("  Successful: {}", metadata.successful_exports);
         println!("  Failed: {}", metadata.failed_exports.len());
+        if metadata.interrupted {
+            println!(
+                "  {}",
+                style("Interrupted before all repositories were exported").yellow()
+            );
+        }
 
-        if !metadata.failed_exports.is_empty() {
+        if manifest.entries().next().is_none() && !metadata.failed_exports.is_empty() {
             println!("\n{}", style("Failed repositories:").red());
             for repo in &metadata.failed_exports {
                 println!("  - {repo}");
@@ -120,32 +259,277 @@ async fn handle_status_command() -> Result<()> {
         println!("{}", style("No export information found").yellow());
     }
 
+    if manifest.entries().next().is_some() {
+        let synced: Vec<_> = manifest
+            .entries()
+            .filter_map(|entry| entry.last_synced)
+            .collect();
+        let size: u64 = manifest.entries().map(|entry| entry.size_bytes).sum();
+
+        println!("\n{}", style("Backed-up repositories:").bold());
+        println!("  Tracked: {}", manifest.entries().count());
+        println!("  Git objects: {}", format_bytes(size));
+        if let (Some(oldest), Some(newest)) = (synced.iter().min(), synced.iter().max()) {
+            println!(
+                "  Last synced: between {} and {}",
+                oldest.format("%Y-%m-%d %H:%M UTC"),
+                newest.format("%Y-%m-%d %H:%M UTC")
+            );
+        }
+
+        let failing: Vec<_> = manifest
+            .entries()
+            .filter(|entry| entry.last_error.is_some())
+            .collect();
+        if !failing.is_empty() {
+            println!("\n{}", style("Failed repositories:").red());
+            for entry in failing {
+                let last_synced = entry
+                    .last_synced
+                    .map_or("never synced".to_string(), |date| {
+                        format!("last synced {}", date.format("%Y-%m-%d"))
+                    });
+                println!(
+                    "  - {}: {} ({} failed attempts in a row, {last_synced})",
+                    entry.full_name,
+                    entry.last_error.as_deref().unwrap_or_default(),
+                    entry.failed_attempts
+                );
+            }
+        }
+    }
+
+    let missing: Vec<_> = manifest
+        .entries()
+        .filter_map(|entry| entry.missing_since.map(|since| (entry, since)))
+        .collect();
+    if !missing.is_empty() {
+        println!(
+            "\n{}",
+            style("Repositories not found on GitHub (moved aside if still missing on the next export):")
+                .yellow()
+        );
+        for (entry, since) in missing {
+            println!(
+                "  - {} (since {})",
+                entry.full_name,
+                since.format("%Y-%m-%d")
+            );
+        }
+    }
+
+    if !manifest.orphaned().is_empty() {
+        println!(
+            "\n{}",
+            style("Repositories deleted upstream (kept locally):").yellow()
+        );
+        for orphan in manifest.orphaned() {
+            println!(
+                "  - {} → {} (since {})",
+                orphan.full_name,
+                orphan.path.display(),
+                orphan.detected_at.format("%Y-%m-%d")
+            );
+        }
+    }
+
     Ok(())
 }
 
-async fn handle_sync_command(cli: Cli, since: Option<String>) -> Result<()> {
+async fn handle_sync_command(cli: Cli, since: Option<String>, full: bool) -> Result<()> {
     let mut config = Config::load()?;
     merge_cli_config(&mut config, &cli);
 
-    if config.github_token.is_none() {
-        return Err(error::GhExportError::Config(
-            "No GitHub token configured. Run without subcommand to set up.".to_string(),
-        ));
+    require_token(&config)?;
+
+    let window = match since {
+        Some(since) => SyncWindow::Since(parse_since(&since)?),
+        None if full => SyncWindow::Everything,
+        None => SyncWindow::SinceLastExport,
+    };
+
+    if cli.output_format == OutputFormat::Text {
+        println!("{}", style("Syncing repositories...").bold());
+    }
+
+    run_export(config, Some(window), RunOptions::from_cli(&cli)).await
+}
+
+async fn handle_list_command(cli: Cli, format: ListFormat) -> Result<()> {
+    let mut config = Config::load()?;
+    merge_cli_config(&mut config, &cli);
+
+    require_token(&config)?;
+
+    let options = RunOptions {
+        preview: Some(format),
+        ..RunOptions::from_cli(&cli)
+    };
+    run_export(config, None, options).await
+}
+
+async fn handle_retry_failed_command(cli: Cli) -> Result<()> {
+    let mut config = Config::load()?;
+    merge_cli_config(&mut config, &cli);
+
+    require_token(&config)?;
+
+    let failed: HashSet<String> = ExportMetadata::find_latest(&config.output_directory)?
+        .map(|metadata| metadata.failed_exports.into_iter().collect())
+        .unwrap_or_default();
+
+    if cli.output_format == OutputFormat::Text {
+        if failed.is_empty() {
+            println!("{}", style("No failed repositories to retry").green());
+        } else {
+            println!(
+                "{} {} failed repositories...",
+                style("Retrying").bold(),
+                failed.len()
+            );
+        }
+    }
+
+    if failed.is_empty() {
+        return Ok(());
+    }
+
+    run_export(
+        config,
+        Some(SyncWindow::Repositories(failed)),
+        RunOptions::from_cli(&cli),
+    )
+    .await
+}
+
+async fn handle_verify_command(cli: Cli, offline: bool) -> Result<()> {
+    let mut config = Config::load()?;
+    merge_cli_config(&mut config, &cli);
+    config.validate()?;
+
+    if !offline {
+        require_token(&config)?;
+    }
+
+    let events = cli.output_format == OutputFormat::Json;
+
+    if !config.output_directory.exists() {
+        if events {
+            Event::VerifySummary {
+                total: 0,
+                ok: 0,
+                stale: 0,
+                corrupted: 0,
+            }
+            .emit();
+        } else {
+            println!(
+                "{} {} does not exist",
+                style("Nothing to verify:").yellow(),
+                config.output_directory.display()
+            );
+        }
+        return Ok(());
+    }
+
+    let client = if offline {
+        None
+    } else {
+        Some(build_client(&config)?)
+    };
+
+    let repositories = find_local_repositories(&config.output_directory)?;
+    let spinner = create_spinner(&format!("Verifying {} repositories...", repositories.len()));
+
+    let mut reports: Vec<_> = futures::stream::iter(&repositories)
+        .map(|repo| async {
+            let report = verify_repository(repo, client.as_ref()).await;
+            (&repo.full_name, report)
+        })
+        .buffer_unordered(config.parallel_downloads)
+        .collect()
+        .await;
+    spinner.finish_and_clear();
+    reports.sort_by(|a, b| a.0.cmp(b.0));
+
+    let mut corrupted = 0;
+    let mut stale = 0;
+
+    for (name, report) in reports {
+        let report = report?;
+
+        if report.corrupted.is_some() {
+            corrupted += 1;
+        } else if !report.stale.is_empty() {
+            stale += 1;
+        }
+
+        if events {
+            Event::Verified {
+                repository: name,
+                result: if report.corrupted.is_some() {
+                    "corrupted"
+                } else if !report.stale.is_empty() {
+                    "stale"
+                } else {
+                    "ok"
+                },
+                reason: report.corrupted.as_deref(),
+                stale: &report.stale,
+                warnings: &report.warnings,
+            }
+            .emit();
+            continue;
+        }
+
+        if let Some(reason) = &report.corrupted {
+            println!("  {} {name}: {reason}", style("✗").red().bold());
+        } else if !report.stale.is_empty() {
+            println!("  {} {name} is out of date:", style("!").yellow().bold());
+            for reason in &report.stale {
+                println!("      - {reason}");
+            }
+        }
+
+        for warning in &report.warnings {
+            println!("  {} {name}: {warning}", style("?").yellow());
+        }
     }
 
-    println!("{}", style("Syncing repositories...").bold());
-    if let Some(since) = since {
-        println!("Only updating repositories modified after: {since}");
+    if events {
+        Event::VerifySummary {
+            total: repositories.len(),
+            ok: repositories.len() - corrupted - stale,
+            stale,
+            corrupted,
+        }
+        .emit();
+    } else {
+        println!("\n{}", style("Verification Summary:").bold());
+        println!("  Total: {}", repositories.len());
+        println!(
+            "  OK: {}",
+            style(repositories.len() - corrupted - stale).green()
+        );
+        println!("  Stale: {}", style(stale).yellow());
+        println!("  Corrupted: {}", style(corrupted).red());
     }
 
-    run_export(config, true).await
+    if corrupted + stale > 0 {
+        return Err(error::GhExportError::Verification(format!(
+            "{corrupted} corrupted and {stale} stale repositories"
+        )));
+    }
+
+    Ok(())
 }
 
 async fn handle_export_command(cli: Cli) -> Result<()> {
     let mut config = Config::load()?;
     merge_cli_config(&mut config, &cli);
+    let options = RunOptions::from_cli(&cli);
 
-    if config.github_token.is_none() && !cli.quiet {
+    if config.github_token.is_none() && !cli.quiet && !cli.dry_run && options.interactive {
         println!("{}", style("Welcome to GitHub Export!").bold().green());
         println!("\nThis tool will help you export all repositories from your GitHub account.\n");
 
@@ -182,93 +566,362 @@ async fn handle_export_command(cli: Cli) -> Result<()> {
         println!("\n{}", style("Configuration saved!").green());
     }
 
+    require_token(&config)?;
+
+    run_export(config, None, options).await
+}
+
+struct RunOptions {
+    preview: Option<ListFormat>,
+    output_format: OutputFormat,
+    assume_yes: bool,
+    interactive: bool,
+}
+
+impl RunOptions {
+    fn from_cli(cli: &Cli) -> Self {
+        Self {
+            preview: cli.dry_run.then_some(match cli.output_format {
+                OutputFormat::Text => ListFormat::Table,
+                OutputFormat::Json => ListFormat::Json,
+            }),
+            output_format: cli.output_format,
+            assume_yes: cli.yes,
+            // Prompts need someone at a terminal to answer them.
+            interactive: !cli.non_interactive
+                && std::io::stdin().is_terminal()
+                && std::io::stderr().is_terminal(),
+        }
+    }
+}
+
+fn require_token(config: &Config) -> Result<()> {
     if config.github_token.is_none() {
         return Err(error::GhExportError::Config(
-            "No GitHub token provided".to_string(),
+            "No GitHub token configured. Pass --token, set GITHUB_TOKEN, or run gh-export in a terminal to set one up."
+                .to_string(),
         ));
     }
+    Ok(())
+}
 
-    run_export(config, false).await
+fn build_client(config: &Config) -> Result<GitHubClient> {
+    GitHubClient::new(
+        config.github_token.clone().unwrap(),
+        &config.api_url,
+        config.git_host.as_deref(),
+        Duration::from_secs(config.max_rate_limit_wait_secs),
+        config
+            .http_cache
+            .then(|| HttpCache::new(&config.output_directory)),
+    )
 }
 
-async fn run_export(config: Config, is_sync: bool) -> Result<()> {
+async fn run_export(
+    mut config: Config,
+    sync: Option<SyncWindow>,
+    options: RunOptions,
+) -> Result<()> {
+    let is_sync = sync.is_some();
+    let preview = options.preview;
+    let events = options.output_format == OutputFormat::Json;
+    let human_output = preview.map_or(!events, |format| format == ListFormat::Table);
     config.validate()?;
-    config.ensure_output_directory()?;
 
+    // A preview must not write anything, including cached API responses.
+    if preview.is_some() {
+        config.http_cache = false;
+    } else {
+        config.ensure_output_directory()?;
+
+        let removed = cleanup_staging_dirs(&config.output_directory)?;
+        if removed > 0 && human_output {
+            println!(
+                "{} {} interrupted clone(s)",
+                style("Cleaned up").yellow(),
+                removed
+            );
+        }
+    }
     let start_time = Instant::now();
-    let client = GitHubClient::new(config.github_token.clone().unwrap())?;
+    let started_at = chrono::Utc::now();
+    let client = build_client(&config)?;
 
     let spinner = create_spinner("Checking authentication...");
     let user = client.get_authenticated_user().await?;
     spinner.finish_and_clear();
 
-    println!(
-        "{} {}",
-        style("Authenticated as:").bold(),
-        style(&user.login).cyan()
-    );
+    if human_output {
+        println!(
+            "{} {}",
+            style("Authenticated as:").bold(),
+            style(&user.login).cyan()
+        );
+    }
+
+    match client.check_rate_limit().await {
+        Ok(rate_limit) => debug!(
+            "API rate limit: {}/{} requests remaining",
+            rate_limit.rate.remaining, rate_limit.rate.limit
+        ),
+        Err(e) => debug!("Could not check rate limit: {}", e),
+    }
 
     let spinner = create_spinner("Fetching repository list...");
-    let mut repositories = client.list_user_repositories(&user.login).await?;
+    let discovered = discover_repositories(&client, &config, &user.login).await?;
     spinner.finish_and_clear();
 
-    if !config.include_archived {
-        repositories.retain(|repo| !repo.archived);
+    let mut manifest = Manifest::load(&config.output_directory)?;
+    let reconciliation = manifest
+        .reconcile(&discovered, &client, preview.is_some())
+        .await?;
+    if preview.is_none() && human_output {
+        for (from, to) in &reconciliation.moved {
+            println!("{} {from} → {to}", style("Moved").cyan());
+        }
+        for name in &reconciliation.restored {
+            println!("{} {name} from {ORPHANED_DIR}/", style("Restored").cyan());
+        }
+        if !reconciliation.orphaned.is_empty() {
+            println!(
+                "{} {} repositories deleted upstream to {}/",
+                style("Moved").yellow(),
+                reconciliation.orphaned.len(),
+                ORPHANED_DIR
+            );
+        }
+        for name in &reconciliation.missing {
+            println!(
+                "{} {name} was not found on GitHub (moved to {ORPHANED_DIR}/ if still missing next run)",
+                style("!").yellow().bold()
+            );
+        }
+    }
+
+    let previous_metadata = ExportMetadata::load(&config.output_directory.join(&user.login))?;
+    let is_retry = matches!(sync, Some(SyncWindow::Repositories(_)));
+
+    let mut plan = plan_repositories(
+        &config,
+        discovered,
+        sync.as_ref(),
+        &manifest,
+        previous_metadata.as_ref(),
+    )?;
+
+    // In a preview nothing has been moved yet, so renamed repositories are
+    // still found under their old name.
+    for planned in &mut plan {
+        if planned.action == PlannedAction::Clone
+            && reconciliation
+                .moved
+                .iter()
+                .map(|(_, to)| to)
+                .chain(&reconciliation.restored)
+                .any(|name| name == &planned.full_name)
+        {
+            planned.action = PlannedAction::Update;
+        }
+    }
+
+    if let Some(format) = preview {
+        plan.extend(
+            reconciliation
+                .orphaned
+                .into_iter()
+                .map(|entry| PlannedRepository {
+                    full_name: entry.full_name,
+                    action: PlannedAction::Orphaned,
+                    repository: entry.metadata,
+                }),
+        );
+        return plan::print_plan(&plan, format);
     }
 
-    if config.exclude_forks {
-        repositories.retain(|repo| !repo.fork);
+    let discovered = plan.len();
+    let unchanged = plan
+        .iter()
+        .filter(|planned| planned.action == PlannedAction::Skip(SKIP_UNCHANGED))
+        .count();
+    match &sync {
+        _ if !human_output => {}
+        Some(SyncWindow::SinceLastExport) => println!(
+            "Only updating repositories modified since their last sync ({unchanged} unchanged skipped)"
+        ),
+        Some(SyncWindow::Since(since)) => println!(
+            "Only updating repositories modified after {} ({unchanged} unchanged skipped)",
+            style(since.format("%Y-%m-%d %H:%M:%S UTC")).cyan(),
+        ),
+        _ => {}
     }
 
+    let refresh_only: HashSet<u64> = plan
+        .iter()
+        .filter(|planned| planned.action == PlannedAction::Refresh)
+        .filter_map(|planned| planned.repository.as_ref().map(|repo| repo.id))
+        .collect();
+    let repositories: Vec<Repository> = plan
+        .into_iter()
+        .filter(|planned| planned.action.is_selected())
+        .filter_map(|planned| planned.repository)
+        .collect();
+
     let total_size: u64 = repositories.iter().map(|r| r.size * 1024).sum();
 
-    println!(
-        "{} {} repositories (estimated size: {})",
-        style("Found").bold(),
-        style(repositories.len()).cyan(),
-        style(format_bytes(total_size)).yellow()
-    );
+    if events {
+        Event::Discovery {
+            discovered,
+            selected: repositories.len(),
+            estimated_bytes: total_size,
+        }
+        .emit();
+    } else {
+        println!(
+            "{} {} repositories (estimated size: {})",
+            style("Found").bold(),
+            style(repositories.len()).cyan(),
+            style(format_bytes(total_size)).yellow()
+        );
+    }
 
     if repositories.is_empty() {
-        println!("{}", style("No repositories to export").yellow());
+        if human_output {
+            println!("{}", style("No repositories to export").yellow());
+        }
         return Ok(());
     }
 
     check_disk_space(&config.output_directory, total_size * 2).await?;
 
-    if !is_sync 
-        && !config.output_directory.join(&user.login).exists()
-        && !Confirm::with_theme(&ColorfulTheme::default())
-            .with_prompt(format!(
-                "Export {} repositories to {}?",
-                repositories.len(),
-                config.output_directory.display()
-            ))
+    if !is_sync && !options.assume_yes && !config.output_directory.join(&user.login).exists() {
+        let prompt = format!(
+            "Export {} repositories to {}?",
+            repositories.len(),
+            config.output_directory.display()
+        );
+        if !options.interactive {
+            return Err(error::GhExportError::Config(format!(
+                "{prompt} Pass --yes to confirm without a terminal."
+            )));
+        } else if !Confirm::with_theme(&ColorfulTheme::default())
+            .with_prompt(prompt)
             .default(true)
             .interact()?
-    {
-        println!("{}", style("Export cancelled").yellow());
-        return Ok(());
+        {
+            if human_output {
+                println!("{}", style("Export cancelled").yellow());
+            }
+            return Ok(());
+        }
     }
 
-    println!("\n{}", style("Starting export...").bold());
+    let credentials = GitCredentials {
+        token: config.github_token.clone().unwrap(),
+        ssh_key: config.ssh_key.clone(),
+        ssh_passphrase: read_ssh_passphrase(&config, options.interactive)?,
+    };
 
-    let progress = ProgressTracker::new(repositories.len());
+    let download_options = DownloadOptions {
+        shallow: config.shallow_clone,
+        with_issues: config.export_issues,
+        with_releases: config.export_releases,
+        with_wikis: config.export_wikis,
+        mirror: config.mirror,
+        protocol: config.protocol,
+        with_lfs: config.lfs,
+        recurse_submodules: config.recurse_submodules,
+    };
+
+    // Only trust the journal for repositories that are still on disk.
+    let journal = Arc::new(Journal::open(
+        &config.output_directory,
+        started_at,
+        journal::fingerprint(
+            repositories.iter().map(|repo| repo.full_name.as_str()),
+            &format!("{download_options:?}"),
+        ),
+        previous_metadata
+            .as_ref()
+            .filter(|metadata| !metadata.interrupted)
+            .map(|metadata| metadata.last_export),
+    )?);
+    let (resumed, pending): (Vec<_>, Vec<_>) = repositories.iter().cloned().partition(|repo| {
+        journal.is_completed(&repo.full_name)
+            && config
+                .output_directory
+                .join(&repo.owner.login)
+                .join(&repo.name)
+                .exists()
+    });
+
+    if let (Some(started), true) = (journal.resumed_from(), human_output) {
+        println!(
+            "{} interrupted export from {} ({} repositories already done)",
+            style("Resuming").bold(),
+            started.format("%Y-%m-%d %H:%M:%S UTC"),
+            style(resumed.len()).cyan()
+        );
+    }
+
+    if human_output {
+        println!("\n{}", style("Starting export...").bold());
+    }
+
+    let shutdown = Shutdown::install();
+    let progress_mode = if events {
+        ProgressMode::Events
+    } else if std::io::stderr().is_terminal() {
+        ProgressMode::Bars
+    } else {
+        ProgressMode::Log
+    };
+    let progress = ProgressTracker::new(pending.len(), progress_mode);
     let downloader = Downloader::new(
         config.output_directory.clone(),
-        config.github_token.unwrap(),
-        config.shallow_clone,
+        credentials,
+        download_options,
+        client,
+        journal.clone(),
         progress.clone(),
+        shutdown.clone(),
     );
 
-    let results = downloader
-        .download_repositories(repositories.clone(), config.parallel_downloads)
+    let mut results = downloader
+        .download_repositories(pending, &refresh_only, config.parallel_downloads)
         .await?;
     progress.finish();
+    drop(downloader);
+
+    let interrupted = shutdown.is_requested();
+    if !interrupted {
+        journal.finish()?;
+    }
+
+    for (name, result) in &results {
+        let Some(repo) = repositories.iter().find(|repo| &repo.full_name == name) else {
+            continue;
+        };
+        match result {
+            DownloadResult::Success(report) => {
+                manifest.record_success(repo, started_at, report.refs.clone(), report.size_bytes)
+            }
+            DownloadResult::Failed(failure) => manifest.record_failure(repo, &failure.to_string()),
+            DownloadResult::Skipped(_) | DownloadResult::Cancelled => manifest.record(repo),
+        }
+    }
+    manifest.save()?;
+
+    for repo in resumed {
+        let result = DownloadResult::Skipped("completed before interruption".to_string());
+        if events {
+            Event::repo_finished(&repo.full_name, &result).emit();
+        }
+        results.push((repo.full_name, result));
+    }
 
     let successful: Vec<_> = results
         .iter()
-        .filter(|(_, result)| matches!(result, DownloadResult::Success))
+        .filter(|(_, result)| matches!(result, DownloadResult::Success(_)))
         .collect();
 
     let failed: Vec<_> = results
@@ -276,39 +929,370 @@ async fn run_export(config: Config, is_sync: bool) -> Result<()> {
         .filter(|(_, result)| matches!(result, DownloadResult::Failed(_)))
         .collect();
 
-    println!("\n{}", style("Export Summary:").bold());
-    println!("  Total: {}", repositories.len());
-    println!("  Successful: {}", style(successful.len()).green());
-    println!("  Failed: {}", style(failed.len()).red());
+    let skipped = results
+        .iter()
+        .filter(|(_, result)| matches!(result, DownloadResult::Skipped(_)))
+        .count();
 
-    if !failed.is_empty() {
-        println!("\n{}", style("Failed repositories:").red());
-        for (name, result) in &failed {
-            if let DownloadResult::Failed(reason) = result {
-                println!("  - {name}: {reason}");
-            }
+    let cancelled = results
+        .iter()
+        .filter(|(_, result)| matches!(result, DownloadResult::Cancelled))
+        .count();
+
+    if events {
+        Event::Summary {
+            total: repositories.len(),
+            successful: successful.len(),
+            failed: failed.len(),
+            skipped,
+            cancelled,
+            duration_seconds: start_time.elapsed().as_secs(),
+            interrupted,
         }
+        .emit();
+    } else {
+        print_summary(&config, repositories.len(), &results);
     }
 
-    let metadata = ExportMetadata {
-        last_export: chrono::Utc::now(),
-        total_repos: repositories.len(),
-        successful_exports: successful.len(),
-        failed_exports: failed.iter().map(|(name, _)| name.clone()).collect(),
-        export_duration_seconds: start_time.elapsed().as_secs(),
+    // An interrupted run keeps the previous export time so that the next sync
+    // still picks up repositories it never got to.
+    let last_export = match (&previous_metadata, interrupted) {
+        (Some(previous), true) => previous.last_export,
+        _ => chrono::Utc::now(),
+    };
+
+    let failed_exports = failed.iter().map(|(name, _)| name.clone()).collect();
+    let metadata = match previous_metadata.filter(|_| is_retry) {
+        // A retry only revisits the failed repositories, so the rest of the
+        // previous export's results still stand.
+        Some(previous) => ExportMetadata {
+            successful_exports: previous.successful_exports + successful.len() + skipped,
+            failed_exports,
+            interrupted: previous.interrupted || interrupted,
+            ..previous
+        },
+        None => ExportMetadata {
+            last_export,
+            total_repos: repositories.len(),
+            successful_exports: successful.len() + skipped,
+            failed_exports,
+            export_duration_seconds: start_time.elapsed().as_secs(),
+            interrupted,
+        },
     };
 
     let user_dir = config.output_directory.join(&user.login);
+    std::fs::create_dir_all(&user_dir)?;
     metadata.save(&user_dir)?;
 
+    // Skipped repositories were not attempted, so they don't count towards a
+    // partial failure.
+    let outcome = if interrupted {
+        Err(error::GhExportError::Interrupted { cancelled })
+    } else if !failed.is_empty() {
+        Err(error::GhExportError::ExportFailed {
+            failed: failed.len(),
+            succeeded: successful.len(),
+            cause: FailureCause::shared(failed.iter().filter_map(|(_, result)| match result {
+                DownloadResult::Failed(failure) => Some(&failure.error),
+                _ => None,
+            })),
+        })
+    } else {
+        Ok(())
+    };
+
+    if events {
+        return outcome;
+    }
+
     let duration = chrono::Duration::seconds(start_time.elapsed().as_secs() as i64);
+    if interrupted {
+        println!(
+            "\n{} Interrupted after {}; run the export again to resume",
+            style("!").yellow().bold(),
+            style(format_duration(duration)).cyan()
+        );
+        return outcome;
+    }
+
     println!(
         "\n{} Completed in {}",
         style("✓").green().bold(),
         style(format_duration(duration)).cyan()
     );
 
-    Ok(())
+    outcome
+}
+
+fn print_summary(config: &Config, total: usize, results: &[(String, DownloadResult)]) {
+    let successful: Vec<_> = results
+        .iter()
+        .filter(|(_, result)| matches!(result, DownloadResult::Success(_)))
+        .collect();
+    let failed: Vec<_> = results
+        .iter()
+        .filter(|(_, result)| matches!(result, DownloadResult::Failed(_)))
+        .collect();
+    let skipped = results
+        .iter()
+        .filter(|(_, result)| matches!(result, DownloadResult::Skipped(_)))
+        .count();
+    let cancelled = results
+        .iter()
+        .filter(|(_, result)| matches!(result, DownloadResult::Cancelled))
+        .count();
+
+    println!("\n{}", style("Export Summary:").bold());
+    println!("  Total: {total}");
+    println!("  Successful: {}", style(successful.len()).green());
+    if skipped > 0 {
+        println!(
+            "  Already done before interruption: {}",
+            style(skipped).cyan()
+        );
+    }
+    println!("  Failed: {}", style(failed.len()).red());
+    if cancelled > 0 {
+        println!("  Not started (interrupted): {}", style(cancelled).yellow());
+    }
+
+    if config.export_wikis {
+        let wiki_count = |status: WikiStatus| {
+            successful
+                .iter()
+                .filter(|(_, result)| {
+                    matches!(result, DownloadResult::Success(report) if report.wiki == Some(status))
+                })
+                .count()
+        };
+        println!(
+            "  Wikis: {} backed up, {} not initialized",
+            style(wiki_count(WikiStatus::Backed)).green(),
+            wiki_count(WikiStatus::NotInitialized)
+        );
+    }
+
+    let lfs_objects: usize = successful
+        .iter()
+        .map(|(_, result)| match result {
+            DownloadResult::Success(report) => report.lfs_objects,
+            _ => 0,
+        })
+        .sum();
+
+    if config.lfs {
+        println!("  LFS objects downloaded: {}", style(lfs_objects).green());
+    }
+
+    let diverged: Vec<_> = successful
+        .iter()
+        .filter_map(|(name, result)| match result {
+            DownloadResult::Success(report) if !report.diverged_branches.is_empty() => {
+                Some((name, &report.diverged_branches))
+            }
+            _ => None,
+        })
+        .collect();
+
+    if !diverged.is_empty() {
+        println!(
+            "\n{}",
+            style("Repositories with branches that could not be fast-forwarded:").yellow()
+        );
+        for (name, branches) in &diverged {
+            println!("  - {name}: {}", branches.join(", "));
+        }
+    }
+
+    if !failed.is_empty() {
+        println!("\n{}", style("Failed repositories:").red());
+        for (name, result) in &failed {
+            if let DownloadResult::Failed(failure) = result {
+                let attempts = match failure.attempts {
+                    1 => String::new(),
+                    n => format!(", {n} attempts"),
+                };
+                println!(
+                    "  - {name}: {failure} ({}{attempts})",
+                    failure.error.category()
+                );
+            }
+        }
+    }
+}
+
+const SKIP_UNCHANGED: &str = "unchanged";
+
+fn plan_repositories(
+    config: &Config,
+    discovered: Vec<Repository>,
+    sync: Option<&SyncWindow>,
+    manifest: &Manifest,
+    previous_metadata: Option<&ExportMetadata>,
+) -> Result<Vec<PlannedRepository>> {
+    let repo_filter = RepoFilter::from_config(config)?;
+
+    let since = match sync {
+        Some(SyncWindow::Since(since)) => Some(*since),
+        Some(SyncWindow::SinceLastExport) => previous_metadata.map(|metadata| {
+            metadata.last_export
+                - chrono::Duration::seconds(metadata.export_duration_seconds as i64)
+        }),
+        Some(SyncWindow::Everything) | Some(SyncWindow::Repositories(_)) | None => None,
+    };
+    // Without an explicit date, each repository is compared against its own
+    // last successful sync, falling back to the last export for new entries.
+    let per_repo = matches!(sync, Some(SyncWindow::SinceLastExport));
+
+    let previously_failed: HashSet<&str> = previous_metadata
+        .iter()
+        .flat_map(|metadata| metadata.failed_exports.iter().map(String::as_str))
+        .collect();
+
+    let is_unchanged = |repo: &Repository, exists: bool| {
+        let entry = manifest.get(repo.id);
+        let Some(repo_since) = entry
+            .filter(|_| per_repo)
+            .and_then(|entry| entry.last_synced)
+            .or(since)
+        else {
+            return false;
+        };
+
+        exists
+            && !is_modified_since(repo, repo_since)
+            && entry.is_none_or(|entry| entry.last_error.is_none())
+            && !previously_failed.contains(repo.full_name.as_str())
+    };
+
+    Ok(discovered
+        .into_iter()
+        .map(|repo| {
+            let exists = config
+                .output_directory
+                .join(&repo.owner.login)
+                .join(&repo.name)
+                .exists();
+
+            let action = if repo.archived && !config.include_archived {
+                PlannedAction::Skip("archived")
+            } else if repo.fork && config.exclude_forks {
+                PlannedAction::Skip("fork")
+            } else if !repo_filter.matches(&repo) {
+                PlannedAction::Skip("filtered out")
+            } else if matches!(sync, Some(SyncWindow::Repositories(names)) if !names.contains(&repo.full_name))
+            {
+                PlannedAction::Skip("did not fail")
+            } else if is_unchanged(&repo, exists) {
+                // Issue and release activity doesn't show up in the dates
+                // GitHub reports for a repository, so those are still exported.
+                if config.export_issues || config.export_releases {
+                    PlannedAction::Refresh
+                } else {
+                    PlannedAction::Skip(SKIP_UNCHANGED)
+                }
+            } else if exists {
+                PlannedAction::Update
+            } else {
+                PlannedAction::Clone
+            };
+
+            PlannedRepository {
+                full_name: repo.full_name.clone(),
+                action,
+                repository: Some(repo),
+            }
+        })
+        .collect())
+}
+
+async fn discover_repositories(
+    client: &GitHubClient,
+    config: &Config,
+    username: &str,
+) -> Result<Vec<Repository>> {
+    let mut repositories = client.list_user_repositories(username).await?;
+
+    let mut organizations = config.organizations.clone();
+    if config.all_organizations {
+        for org in client.list_user_organizations().await? {
+            if !organizations
+                .iter()
+                .any(|o| o.eq_ignore_ascii_case(&org.login))
+            {
+                organizations.push(org.login);
+            }
+        }
+    }
+
+    let mut seen: HashSet<u64> = repositories.iter().map(|repo| repo.id).collect();
+    for org in &organizations {
+        for repo in client.list_org_repositories(org).await? {
+            if seen.insert(repo.id) {
+                repositories.push(repo);
+            }
+        }
+    }
+
+    Ok(repositories)
+}
+
+fn read_ssh_passphrase(config: &Config, interactive: bool) -> Result<Option<String>> {
+    let Some(ssh_key) = config
+        .ssh_key
+        .as_ref()
+        .filter(|_| config.protocol == Protocol::Ssh)
+    else {
+        return Ok(None);
+    };
+
+    if let Ok(passphrase) = std::env::var("GH_EXPORT_SSH_PASSPHRASE") {
+        return Ok(Some(passphrase));
+    }
+
+    if !ssh_key_is_encrypted(ssh_key)? {
+        return Ok(None);
+    }
+
+    if !interactive {
+        return Err(error::GhExportError::Config(format!(
+            "{} is encrypted; set GH_EXPORT_SSH_PASSPHRASE to use it without a terminal",
+            ssh_key.display()
+        )));
+    }
+
+    let passphrase = rpassword::prompt_password(format!("Passphrase for {}: ", ssh_key.display()))?;
+    Ok(Some(passphrase))
+}
+
+enum SyncWindow {
+    Everything,
+    Since(chrono::DateTime<chrono::Utc>),
+    SinceLastExport,
+    Repositories(HashSet<String>),
+}
+
+fn parse_since(value: &str) -> Result<chrono::DateTime<chrono::Utc>> {
+    if let Ok(date) = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_time(chrono::NaiveTime::MIN).and_utc());
+    }
+
+    chrono::DateTime::parse_from_rfc3339(value)
+        .map(|date| date.with_timezone(&chrono::Utc))
+        .map_err(|_| {
+            error::GhExportError::Config(format!(
+                "Invalid --since value '{value}': expected YYYY-MM-DD or an RFC 3339 timestamp"
+            ))
+        })
+}
+
+fn is_modified_since(repo: &Repository, since: chrono::DateTime<chrono::Utc>) -> bool {
+    [repo.pushed_at.as_deref(), Some(repo.updated_at.as_str())]
+        .into_iter()
+        .flatten()
+        .filter_map(|value| chrono::DateTime::parse_from_rfc3339(value).ok())
+        .any(|date| date >= since)
 }
 
 fn merge_cli_config(config: &mut Config, cli: &Cli) {
@@ -316,6 +1300,30 @@ fn merge_cli_config(config: &mut Config, cli: &Cli) {
         config.github_token = Some(token.clone());
     }
 
+    if let Some(api_url) = &cli.api_url {
+        config.api_url = api_url.clone();
+    }
+
+    if let Some(git_host) = &cli.git_host {
+        config.git_host = Some(git_host.clone());
+    }
+
+    if let Some(protocol) = cli.protocol {
+        config.protocol = protocol;
+    }
+
+    if let Some(ssh_key) = &cli.ssh_key {
+        config.ssh_key = Some(ssh_key.clone());
+    }
+
+    if cli.no_cache {
+        config.http_cache = false;
+    }
+
+    if let Some(max_wait) = cli.max_rate_limit_wait {
+        config.max_rate_limit_wait_secs = max_wait;
+    }
+
     if let Some(output) = &cli.output {
         config.output_directory = output.clone();
     }
@@ -335,6 +1343,70 @@ fn merge_cli_config(config: &mut Config, cli: &Cli) {
     if cli.shallow {
         config.shallow_clone = true;
     }
+
+    if cli.mirror {
+        config.mirror = true;
+    }
+
+    if cli.recurse_submodules {
+        config.recurse_submodules = true;
+    }
+
+    if cli.with_lfs {
+        config.lfs = true;
+    }
+
+    if cli.with_issues {
+        config.export_issues = true;
+    }
+
+    if cli.with_releases {
+        config.export_releases = true;
+    }
+
+    if cli.with_wikis {
+        config.export_wikis = true;
+    }
+
+    if !cli.orgs.is_empty() {
+        config.organizations = cli.orgs.clone();
+    }
+
+    if cli.all_orgs {
+        config.all_organizations = true;
+    }
+
+    if !cli.include.is_empty() {
+        config.include = cli.include.clone();
+    }
+
+    if let Some(filter) = &cli.filter {
+        config.name_filter = Some(filter.clone());
+    }
+
+    if !cli.exclude.is_empty() {
+        config.exclude = cli.exclude.clone();
+    }
+
+    if !cli.languages.is_empty() {
+        config.languages = cli.languages.clone();
+    }
+
+    if !cli.topics.is_empty() {
+        config.topics = cli.topics.clone();
+    }
+
+    if let Some(visibility) = cli.visibility {
+        config.visibility = visibility;
+    }
+
+    if cli.min_size.is_some() {
+        config.min_size_kb = cli.min_size;
+    }
+
+    if cli.max_size.is_some() {
+        config.max_size_kb = cli.max_size;
+    }
 }
 
 fn format_bytes(bytes: u64) -> String {
@@ -363,3 +1435,207 @@ fn format_duration(duration: chrono::Duration) -> String {
         format!("{seconds}s")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn repository(id: u64, name: &str, pushed_at: &str) -> Repository {
+        serde_json::from_value(serde_json::json!({
+            "id": id,
+            "name": name,
+            "full_name": format!("octocat/{name}"),
+            "owner": {"login": "octocat", "id": 1, "type": "User"},
+            "private": false,
+            "html_url": "",
+            "description": null,
+            "fork": false,
+            "created_at": "2023-01-01T00:00:00Z",
+            "updated_at": pushed_at,
+            "pushed_at": pushed_at,
+            "clone_url": "",
+            "ssh_url": "",
+            "size": 1,
+            "stargazers_count": 0,
+            "watchers_count": 0,
+            "language": null,
+            "archived": false,
+            "disabled": false,
+            "default_branch": "main"
+        }))
+        .unwrap()
+    }
+
+    fn date(value: &str) -> chrono::DateTime<chrono::Utc> {
+        parse_since(value).unwrap()
+    }
+
+    fn metadata(last_export: &str, failed: &[&str]) -> ExportMetadata {
+        ExportMetadata {
+            last_export: date(last_export),
+            total_repos: 0,
+            successful_exports: 0,
+            failed_exports: failed.iter().map(|name| name.to_string()).collect(),
+            export_duration_seconds: 0,
+            interrupted: false,
+        }
+    }
+
+    // Exports into a fresh directory in which `existing` are already cloned.
+    fn plan(
+        repositories: Vec<Repository>,
+        existing: &[&str],
+        sync: SyncWindow,
+        configure: impl FnOnce(&mut Manifest),
+        previous: Option<ExportMetadata>,
+    ) -> Vec<(String, PlannedAction)> {
+        let output_dir = tempfile::tempdir().unwrap();
+        for name in existing {
+            std::fs::create_dir_all(output_dir.path().join("octocat").join(name)).unwrap();
+        }
+        let config = Config {
+            output_directory: output_dir.path().to_path_buf(),
+            ..Config::default()
+        };
+        let mut manifest = Manifest::load(output_dir.path()).unwrap();
+        configure(&mut manifest);
+
+        plan_repositories(
+            &config,
+            repositories,
+            Some(&sync),
+            &manifest,
+            previous.as_ref(),
+        )
+        .unwrap()
+        .into_iter()
+        .map(|planned| (planned.full_name, planned.action))
+        .collect()
+    }
+
+    #[test]
+    fn since_skips_repositories_not_modified_after_the_date() {
+        let mut updated = repository(3, "updated", "2024-01-01T00:00:00Z");
+        updated.updated_at = "2024-07-01T00:00:00Z".to_string();
+
+        let planned = plan(
+            vec![
+                repository(1, "unchanged", "2024-01-01T00:00:00Z"),
+                repository(2, "pushed", "2024-07-01T00:00:00Z"),
+                updated,
+                repository(4, "missing", "2024-01-01T00:00:00Z"),
+            ],
+            &["unchanged", "pushed", "updated"],
+            SyncWindow::Since(date("2024-06-01")),
+            |_| {},
+            None,
+        );
+
+        assert_eq!(
+            planned,
+            vec![
+                (
+                    "octocat/unchanged".to_string(),
+                    PlannedAction::Skip(SKIP_UNCHANGED)
+                ),
+                ("octocat/pushed".to_string(), PlannedAction::Update),
+                ("octocat/updated".to_string(), PlannedAction::Update),
+                ("octocat/missing".to_string(), PlannedAction::Clone),
+            ]
+        );
+    }
+
+    #[test]
+    fn sync_compares_against_last_synced_and_falls_back_to_last_export() {
+        let synced = repository(1, "synced", "2024-07-01T00:00:00Z");
+        let stale = repository(2, "stale", "2024-09-01T00:00:00Z");
+
+        let planned = plan(
+            vec![
+                synced.clone(),
+                stale.clone(),
+                repository(3, "untracked-old", "2024-01-01T00:00:00Z"),
+                repository(4, "untracked-new", "2024-07-01T00:00:00Z"),
+            ],
+            &["synced", "stale", "untracked-old", "untracked-new"],
+            SyncWindow::SinceLastExport,
+            |manifest| {
+                manifest.record_success(&synced, date("2024-08-01"), BTreeMap::new(), 0);
+                manifest.record_success(&stale, date("2024-08-01"), BTreeMap::new(), 0);
+            },
+            Some(metadata("2024-06-01T00:00:00Z", &[])),
+        );
+
+        assert_eq!(
+            planned,
+            vec![
+                (
+                    "octocat/synced".to_string(),
+                    PlannedAction::Skip(SKIP_UNCHANGED)
+                ),
+                ("octocat/stale".to_string(), PlannedAction::Update),
+                (
+                    "octocat/untracked-old".to_string(),
+                    PlannedAction::Skip(SKIP_UNCHANGED)
+                ),
+                ("octocat/untracked-new".to_string(), PlannedAction::Update),
+            ]
+        );
+    }
+
+    #[test]
+    fn sync_never_skips_previously_failed_repositories() {
+        let failing = repository(2, "failing", "2024-01-01T00:00:00Z");
+
+        let planned = plan(
+            vec![
+                repository(1, "flaky", "2024-01-01T00:00:00Z"),
+                failing.clone(),
+            ],
+            &["flaky", "failing"],
+            SyncWindow::SinceLastExport,
+            |manifest| manifest.record_failure(&failing, "Fetch failed"),
+            Some(metadata("2024-06-01T00:00:00Z", &["octocat/flaky"])),
+        );
+
+        assert!(planned
+            .iter()
+            .all(|(_, action)| *action == PlannedAction::Update));
+    }
+
+    #[test]
+    fn unchanged_repositories_still_refresh_issues_and_releases() {
+        let output_dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(output_dir.path().join("octocat/unchanged")).unwrap();
+        let config = Config {
+            output_directory: output_dir.path().to_path_buf(),
+            export_issues: true,
+            ..Config::default()
+        };
+
+        let planned = plan_repositories(
+            &config,
+            vec![repository(1, "unchanged", "2024-01-01T00:00:00Z")],
+            Some(&SyncWindow::Since(date("2024-06-01"))),
+            &Manifest::load(output_dir.path()).unwrap(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(planned[0].action, PlannedAction::Refresh);
+    }
+
+    #[test]
+    fn full_sync_updates_everything() {
+        let planned = plan(
+            vec![repository(1, "unchanged", "2024-01-01T00:00:00Z")],
+            &["unchanged"],
+            SyncWindow::Everything,
+            |_| {},
+            Some(metadata("2024-06-01T00:00:00Z", &[])),
+        );
+
+        assert_eq!(planned[0].1, PlannedAction::Update);
+    }
+}
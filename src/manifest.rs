@@ -0,0 +1,314 @@
+use crate::error::{GhExportError, Result};
+use crate::github::{GitHubClient, Repository};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+const MANIFEST_FILE: &str = ".gh-export-manifest.json";
+pub const ORPHANED_DIR: &str = "_orphaned";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub full_name: String,
+    #[serde(default)]
+    pub metadata: Option<Repository>,
+    #[serde(default)]
+    pub last_synced: Option<chrono::DateTime<chrono::Utc>>,
+    #[serde(default)]
+    pub refs: BTreeMap<String, String>,
+    #[serde(default)]
+    pub size_bytes: u64,
+    #[serde(default)]
+    pub last_error: Option<String>,
+    // Consecutive failures; reset by the next successful sync.
+    #[serde(default)]
+    pub failed_attempts: u32,
+    #[serde(default)]
+    pub missing_since: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrphanedRepository {
+    pub id: u64,
+    pub full_name: String,
+    pub path: PathBuf,
+    pub detected_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ManifestData {
+    #[serde(default)]
+    repositories: BTreeMap<u64, ManifestEntry>,
+    #[serde(default)]
+    orphaned: Vec<OrphanedRepository>,
+}
+
+#[derive(Debug, Default)]
+pub struct Reconciliation {
+    pub moved: Vec<(String, String)>,
+    pub orphaned: Vec<ManifestEntry>,
+    pub restored: Vec<String>,
+    pub missing: Vec<String>,
+}
+
+pub struct Manifest {
+    path: PathBuf,
+    output_dir: PathBuf,
+    data: ManifestData,
+}
+
+impl Manifest {
+    pub fn load(output_dir: &Path) -> Result<Self> {
+        let path = output_dir.join(MANIFEST_FILE);
+        let data = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            ManifestData::default()
+        };
+
+        Ok(Self {
+            path,
+            output_dir: output_dir.to_path_buf(),
+            data,
+        })
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let partial_path = self.path.with_extension("json.part");
+        fs::write(&partial_path, serde_json::to_string_pretty(&self.data)?)?;
+        fs::rename(&partial_path, &self.path)?;
+        Ok(())
+    }
+
+    pub fn orphaned(&self) -> &[OrphanedRepository] {
+        &self.data.orphaned
+    }
+
+    pub fn get(&self, id: u64) -> Option<&ManifestEntry> {
+        self.data.repositories.get(&id)
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &ManifestEntry> {
+        self.data.repositories.values()
+    }
+
+    pub fn record(&mut self, repo: &Repository) {
+        if self.local_path(&repo.full_name).exists() {
+            self.entry(repo);
+        }
+    }
+
+    pub fn record_success(
+        &mut self,
+        repo: &Repository,
+        synced_at: chrono::DateTime<chrono::Utc>,
+        refs: BTreeMap<String, String>,
+        size_bytes: u64,
+    ) {
+        let entry = self.entry(repo);
+        entry.last_synced = Some(synced_at);
+        entry.refs = refs;
+        entry.size_bytes = size_bytes;
+        entry.last_error = None;
+        entry.failed_attempts = 0;
+    }
+
+    pub fn record_failure(&mut self, repo: &Repository, reason: &str) {
+        let entry = self.entry(repo);
+        entry.last_error = Some(reason.to_string());
+        entry.failed_attempts += 1;
+    }
+
+    fn entry(&mut self, repo: &Repository) -> &mut ManifestEntry {
+        let entry = self.data.repositories.entry(repo.id).or_default();
+        entry.full_name = repo.full_name.clone();
+        entry.metadata = Some(repo.clone());
+        entry.missing_since = None;
+        entry
+    }
+
+    // Brings the output directory in line with GitHub: renamed or transferred
+    // repositories are moved to their new path and repositories that no longer
+    // exist are moved aside into `_orphaned/`. Nothing is ever deleted. With
+    // `dry_run`, only reports what would happen.
+    //
+    // GitHub also answers 404 when the token has merely lost access to a
+    // repository (an organization's SSO not authorized, removal from the
+    // organization), so a repository is only flagged as missing the first
+    // time and moved aside once it is still missing on the next run.
+    pub async fn reconcile(
+        &mut self,
+        discovered: &[Repository],
+        client: &GitHubClient,
+        dry_run: bool,
+    ) -> Result<Reconciliation> {
+        let mut reconciliation = Reconciliation::default();
+        let discovered_ids: HashSet<u64> = discovered.iter().map(|repo| repo.id).collect();
+
+        for repo in discovered {
+            if let Some(index) = self
+                .data
+                .orphaned
+                .iter()
+                .position(|orphan| orphan.id == repo.id)
+            {
+                let orphan = self.data.orphaned.remove(index);
+                if self.move_repository(&orphan.path, &repo.full_name, dry_run)? {
+                    reconciliation.restored.push(repo.full_name.clone());
+                }
+                continue;
+            }
+
+            let Some(entry) = self.data.repositories.get(&repo.id) else {
+                continue;
+            };
+            if entry.full_name != repo.full_name {
+                let old_name = entry.full_name.clone();
+                if self.move_repository(&self.local_path(&old_name), &repo.full_name, dry_run)? {
+                    reconciliation
+                        .moved
+                        .push((old_name, repo.full_name.clone()));
+                }
+            }
+        }
+
+        let missing: Vec<(u64, String)> = self
+            .data
+            .repositories
+            .iter()
+            .filter(|(id, _)| !discovered_ids.contains(id))
+            .map(|(id, entry)| (*id, entry.full_name.clone()))
+            .collect();
+
+        for (id, full_name) in missing {
+            let local_path = self.local_path(&full_name);
+            if !local_path.exists() {
+                self.data.repositories.remove(&id);
+                continue;
+            }
+
+            match client.get_repository_by_id(id).await {
+                Ok(remote) if remote.full_name != full_name => {
+                    if self.move_repository(&local_path, &remote.full_name, dry_run)? {
+                        reconciliation
+                            .moved
+                            .push((full_name, remote.full_name.clone()));
+                    }
+                    self.record(&remote);
+                }
+                // Still on GitHub, just outside the current selection.
+                Ok(_) => {
+                    if let Some(entry) = self.data.repositories.get_mut(&id) {
+                        entry.missing_since = None;
+                    }
+                }
+                Err(GhExportError::NotFound(_)) => {
+                    let Some(entry) = self.data.repositories.get_mut(&id) else {
+                        continue;
+                    };
+                    if entry.missing_since.is_none() {
+                        if !dry_run {
+                            warn!(
+                                "{} was not found on GitHub; it will be moved to {}/ if it is still missing on the next run",
+                                full_name, ORPHANED_DIR
+                            );
+                            entry.missing_since = Some(chrono::Utc::now());
+                        }
+                        reconciliation.missing.push(full_name);
+                        continue;
+                    }
+
+                    let Some(entry) = self.data.repositories.remove(&id) else {
+                        continue;
+                    };
+                    if !dry_run {
+                        let orphan_path = self.orphan_path(&full_name, id);
+                        info!(
+                            "{} no longer exists on GitHub, moving it to {}",
+                            full_name,
+                            orphan_path.display()
+                        );
+                        move_with_wiki(&local_path, &orphan_path)?;
+                        self.data.orphaned.push(OrphanedRepository {
+                            id,
+                            full_name,
+                            path: orphan_path,
+                            detected_at: chrono::Utc::now(),
+                        });
+                    }
+                    reconciliation.orphaned.push(entry);
+                }
+                Err(e) => warn!("Could not look up {} on GitHub: {}", full_name, e),
+            }
+        }
+
+        if !dry_run {
+            for repo in discovered {
+                self.record(repo);
+            }
+            self.save()?;
+        }
+
+        Ok(reconciliation)
+    }
+
+    fn move_repository(&self, from: &Path, full_name: &str, dry_run: bool) -> Result<bool> {
+        let to = self.local_path(full_name);
+        if !from.exists() {
+            return Ok(false);
+        }
+        if to.exists() {
+            warn!(
+                "Not moving {} to {}: the destination already exists",
+                from.display(),
+                to.display()
+            );
+            return Ok(false);
+        }
+
+        if !dry_run {
+            info!("Moving {} to {}", from.display(), to.display());
+            move_with_wiki(from, &to)?;
+        }
+        Ok(true)
+    }
+
+    fn local_path(&self, full_name: &str) -> PathBuf {
+        self.output_dir.join(full_name)
+    }
+
+    fn orphan_path(&self, full_name: &str, id: u64) -> PathBuf {
+        let path = self.output_dir.join(ORPHANED_DIR).join(full_name);
+        if path.exists() {
+            path.with_file_name(format!(
+                "{}-{id}",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            ))
+        } else {
+            path
+        }
+    }
+}
+
+fn move_with_wiki(from: &Path, to: &Path) -> Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(from, to)?;
+
+    let from_wiki = wiki_path(from);
+    let to_wiki = wiki_path(to);
+    if from_wiki.exists() && !to_wiki.exists() {
+        fs::rename(from_wiki, to_wiki)?;
+    }
+
+    Ok(())
+}
+
+fn wiki_path(repo_path: &Path) -> PathBuf {
+    let mut file_name = repo_path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".wiki");
+    repo_path.with_file_name(file_name)
+}
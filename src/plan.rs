@@ -0,0 +1,174 @@
+use crate::cli::ListFormat;
+use crate::error::Result;
+use crate::format_bytes;
+use crate::github::Repository;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlannedAction {
+    Clone,
+    Update,
+    // The git data is unchanged, so only issues and releases are exported.
+    Refresh,
+    Skip(&'static str),
+    Orphaned,
+}
+
+impl PlannedAction {
+    pub fn is_selected(&self) -> bool {
+        matches!(
+            self,
+            PlannedAction::Clone | PlannedAction::Update | PlannedAction::Refresh
+        )
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            PlannedAction::Clone => "clone",
+            PlannedAction::Update => "update",
+            PlannedAction::Refresh => "refresh",
+            PlannedAction::Skip(_) => "skip",
+            PlannedAction::Orphaned => "orphaned",
+        }
+    }
+
+    fn reason(&self) -> Option<&'static str> {
+        match self {
+            PlannedAction::Skip(reason) => Some(reason),
+            PlannedAction::Refresh => Some("unchanged, issues and releases only"),
+            PlannedAction::Orphaned => Some("deleted upstream"),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PlannedRepository {
+    pub full_name: String,
+    pub action: PlannedAction,
+    pub repository: Option<Repository>,
+}
+
+#[derive(Serialize)]
+struct PlanRecord<'a> {
+    repository: &'a str,
+    action: &'static str,
+    reason: Option<&'static str>,
+    size_kb: Option<u64>,
+    visibility: Option<&'static str>,
+    fork: Option<bool>,
+    archived: Option<bool>,
+}
+
+impl<'a> From<&'a PlannedRepository> for PlanRecord<'a> {
+    fn from(planned: &'a PlannedRepository) -> Self {
+        let repo = planned.repository.as_ref();
+        Self {
+            repository: &planned.full_name,
+            action: planned.action.name(),
+            reason: planned.action.reason(),
+            size_kb: repo.map(|repo| repo.size),
+            visibility: repo.map(|repo| if repo.private { "private" } else { "public" }),
+            fork: repo.map(|repo| repo.fork),
+            archived: repo.map(|repo| repo.archived),
+        }
+    }
+}
+
+pub fn print_plan(plan: &[PlannedRepository], format: ListFormat) -> Result<()> {
+    let records: Vec<PlanRecord> = plan.iter().map(PlanRecord::from).collect();
+
+    match format {
+        ListFormat::Json => println!("{}", serde_json::to_string_pretty(&records)?),
+        ListFormat::Csv => {
+            println!("repository,action,reason,size_kb,visibility,fork,archived");
+            for record in &records {
+                let fields = [
+                    record.repository.to_string(),
+                    record.action.to_string(),
+                    record.reason.unwrap_or_default().to_string(),
+                    optional(record.size_kb),
+                    record.visibility.unwrap_or_default().to_string(),
+                    optional(record.fork),
+                    optional(record.archived),
+                ];
+                let fields: Vec<_> = fields.iter().map(|field| csv_field(field)).collect();
+                println!("{}", fields.join(","));
+            }
+        }
+        ListFormat::Table => print_table(&records),
+    }
+
+    Ok(())
+}
+
+fn print_table(records: &[PlanRecord]) {
+    let rows: Vec<[String; 5]> = records
+        .iter()
+        .map(|record| {
+            let mut flags = Vec::new();
+            if record.fork == Some(true) {
+                flags.push("fork");
+            }
+            if record.archived == Some(true) {
+                flags.push("archived");
+            }
+            if let Some(reason) = record.reason {
+                flags.push(reason);
+            }
+
+            [
+                record.action.to_string(),
+                record.repository.to_string(),
+                record
+                    .size_kb
+                    .map_or("-".to_string(), |size| format_bytes(size * 1024)),
+                record.visibility.unwrap_or("-").to_string(),
+                flags.join(", "),
+            ]
+        })
+        .collect();
+
+    let header = ["ACTION", "REPOSITORY", "SIZE", "VISIBILITY", "NOTES"].map(str::to_string);
+    let mut widths = header.clone().map(|column| column.len());
+    for row in &rows {
+        for (width, column) in widths.iter_mut().zip(row) {
+            *width = (*width).max(column.chars().count());
+        }
+    }
+
+    for row in std::iter::once(&header).chain(&rows) {
+        let line: Vec<_> = row
+            .iter()
+            .zip(widths)
+            .map(|(column, width)| format!("{column:<width$}"))
+            .collect();
+        println!("{}", line.join("  ").trim_end());
+    }
+
+    let count = |name: &str| {
+        records
+            .iter()
+            .filter(|record| record.action == name)
+            .count()
+    };
+    println!(
+        "\n{} to clone, {} to update, {} skipped, {} orphaned",
+        count("clone"),
+        count("update"),
+        count("skip"),
+        count("orphaned")
+    );
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -1,21 +1,42 @@
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use crate::download::DownloadResult;
+use crate::events::Event;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+const LOG_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressMode {
+    Bars,
+    // Without a terminal, bars are replaced by periodic log lines.
+    Log,
+    Events,
+}
 
 pub struct ProgressTracker {
     multi_progress: MultiProgress,
     main_bar: ProgressBar,
     repo_bars: Arc<Mutex<HashMap<String, ProgressBar>>>,
+    reported_progress: Mutex<HashMap<String, u32>>,
+    mode: ProgressMode,
+    last_logged: Mutex<Instant>,
     total_repos: AtomicUsize,
     completed_repos: AtomicUsize,
     failed_repos: AtomicUsize,
 }
 
 impl ProgressTracker {
-    pub fn new(total_repos: usize) -> Arc<Self> {
-        let multi_progress = MultiProgress::new();
+    pub fn new(total_repos: usize, mode: ProgressMode) -> Arc<Self> {
+        // In event mode stdout carries JSON lines, so the bars are not drawn.
+        let multi_progress = if mode == ProgressMode::Bars {
+            MultiProgress::new()
+        } else {
+            MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+        };
 
         let main_bar = multi_progress.add(ProgressBar::new(total_repos as u64));
         main_bar.set_style(
@@ -30,13 +51,42 @@ impl ProgressTracker {
             multi_progress,
             main_bar,
             repo_bars: Arc::new(Mutex::new(HashMap::new())),
+            reported_progress: Mutex::new(HashMap::new()),
+            mode,
+            last_logged: Mutex::new(Instant::now()),
             total_repos: AtomicUsize::new(total_repos),
             completed_repos: AtomicUsize::new(0),
             failed_repos: AtomicUsize::new(0),
         })
     }
 
+    pub fn repo_started(&self, repo_name: &str) {
+        if self.mode == ProgressMode::Events {
+            Event::RepoStarted {
+                repository: repo_name,
+            }
+            .emit();
+        }
+    }
+
+    pub fn repo_finished(&self, repo_name: &str, result: &DownloadResult) {
+        match (self.mode, result) {
+            (ProgressMode::Events, _) => {
+                self.reported_progress.lock().unwrap().remove(repo_name);
+                Event::repo_finished(repo_name, result).emit();
+            }
+            (ProgressMode::Log, DownloadResult::Failed(failure)) => {
+                warn!("{} failed: {}", repo_name, failure)
+            }
+            _ => {}
+        }
+    }
+
     pub fn update_repo_progress(&self, repo_name: &str, current: u32, total: u32) {
+        if self.mode == ProgressMode::Events {
+            self.report_progress(repo_name, current, total);
+        }
+
         let mut bars = self.repo_bars.lock().unwrap();
 
         let bar = bars.entry(repo_name.to_string()).or_insert_with(|| {
@@ -59,6 +109,24 @@ impl ProgressTracker {
         }
     }
 
+    // Emits at most one event per 10% of a transfer.
+    fn report_progress(&self, repo_name: &str, current: u32, total: u32) {
+        let step = (current as u64 * 10 / total.max(1) as u64) as u32;
+        let mut reported = self.reported_progress.lock().unwrap();
+        if reported.get(repo_name) == Some(&step) {
+            return;
+        }
+        reported.insert(repo_name.to_string(), step);
+        drop(reported);
+
+        Event::Progress {
+            repository: repo_name,
+            received_objects: current,
+            total_objects: total,
+        }
+        .emit();
+    }
+
     pub fn increment_completed(&self) {
         let completed = self.completed_repos.fetch_add(1, Ordering::SeqCst) + 1;
         self.main_bar.inc(1);
@@ -78,7 +146,11 @@ impl ProgressTracker {
     fn update_main_message(&self) {
         let completed = self.completed_repos.load(Ordering::SeqCst);
         let failed = self.failed_repos.load(Ordering::SeqCst);
-        let _total = self.total_repos.load(Ordering::SeqCst);
+        let total = self.total_repos.load(Ordering::SeqCst);
+
+        if self.mode == ProgressMode::Log {
+            self.log_progress(completed, failed, total);
+        }
 
         let msg = if failed > 0 {
             format!("Completed: {completed}, Failed: {failed}")
@@ -89,6 +161,21 @@ impl ProgressTracker {
         self.main_bar.set_message(msg);
     }
 
+    fn log_progress(&self, completed: usize, failed: usize, total: usize) {
+        let mut last_logged = self.last_logged.lock().unwrap();
+        if completed + failed < total && last_logged.elapsed() < LOG_INTERVAL {
+            return;
+        }
+        *last_logged = Instant::now();
+
+        info!(
+            "Progress: {}/{} repositories done ({} failed)",
+            completed + failed,
+            total,
+            failed
+        );
+    }
+
     pub fn finish(&self) {
         let completed = self.completed_repos.load(Ordering::SeqCst);
         let failed = self.failed_repos.load(Ordering::SeqCst);
@@ -0,0 +1,213 @@
+use crate::download::export_data_dir;
+use crate::error::{GhExportError, Result};
+use crate::github::{GitHubClient, Release, ReleaseAsset, Repository};
+use futures::StreamExt;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use tokio::io::AsyncWriteExt;
+use tracing::{debug, warn};
+
+// SHA-256 of every downloaded asset, so assets GitHub has no digest for can
+// still be verified before they are skipped.
+const CHECKSUMS_FILE: &str = "checksums.json";
+
+pub async fn export_releases(
+    client: &GitHubClient,
+    repo: &Repository,
+    repo_path: &Path,
+) -> Result<()> {
+    let releases_dir = export_data_dir(repo_path)?.join("releases");
+    let releases = client.list_releases(&repo.full_name).await?;
+
+    for raw_release in releases {
+        let release: Release = serde_json::from_value(raw_release.clone())?;
+        let release_dir = releases_dir.join(release_dir_name(&release));
+        fs::create_dir_all(&release_dir)?;
+
+        fs::write(
+            release_dir.join("release.json"),
+            serde_json::to_string_pretty(&raw_release)?,
+        )?;
+
+        let assets_dir = release_dir.join("assets");
+        if !release.assets.is_empty() {
+            fs::create_dir_all(&assets_dir)?;
+        }
+
+        let checksums_path = release_dir.join(CHECKSUMS_FILE);
+        let mut checksums = load_checksums(&checksums_path)?;
+
+        for asset in &release.assets {
+            let file_name = asset_file_name(asset);
+            let asset_path = assets_dir.join(&file_name);
+            let expected = asset
+                .digest
+                .as_deref()
+                .and_then(|digest| digest.strip_prefix("sha256:"))
+                .or(checksums.get(&file_name).map(String::as_str))
+                .map(str::to_string);
+
+            if is_asset_present(&asset_path, asset, expected).await? {
+                debug!("Skipping existing release asset {}", asset_path.display());
+                continue;
+            }
+
+            let checksum = download_asset(client, asset, &asset_path).await?;
+            checksums.insert(file_name, checksum);
+            fs::write(&checksums_path, serde_json::to_string_pretty(&checksums)?)?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn download_asset(
+    client: &GitHubClient,
+    asset: &ReleaseAsset,
+    target: &Path,
+) -> Result<String> {
+    debug!("Downloading release asset {}", asset.name);
+
+    let partial_path = target.with_file_name(format!(
+        "{}.part",
+        target.file_name().unwrap_or_default().to_string_lossy()
+    ));
+
+    let response = client.get_release_asset(asset).await?;
+    let mut stream = response.bytes_stream();
+    let mut file = tokio::fs::File::create(&partial_path).await?;
+    let mut hasher = Sha256::new();
+    let mut written = 0u64;
+
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        hasher.update(&chunk);
+        written += chunk.len() as u64;
+        file.write_all(&chunk).await?;
+    }
+
+    file.flush().await?;
+    drop(file);
+
+    if written != asset.size {
+        let _ = fs::remove_file(&partial_path);
+        return Err(GhExportError::Download(format!(
+            "Size mismatch for release asset {}: expected {} bytes, got {written}",
+            asset.name, asset.size
+        )));
+    }
+
+    let actual = format!("{:x}", hasher.finalize());
+    if let Some(expected) = asset
+        .digest
+        .as_deref()
+        .and_then(|digest| digest.strip_prefix("sha256:"))
+    {
+        if !actual.eq_ignore_ascii_case(expected) {
+            let _ = fs::remove_file(&partial_path);
+            return Err(GhExportError::Download(format!(
+                "Checksum mismatch for release asset {}: expected {expected}, got {actual}",
+                asset.name
+            )));
+        }
+    }
+
+    fs::rename(&partial_path, target)?;
+    Ok(actual)
+}
+
+// An asset on disk is only kept when it has the expected size and its
+// checksum still matches; without a known checksum it is downloaded again.
+async fn is_asset_present(
+    path: &Path,
+    asset: &ReleaseAsset,
+    expected: Option<String>,
+) -> Result<bool> {
+    let size_matches = fs::metadata(path)
+        .map(|metadata| metadata.is_file() && metadata.len() == asset.size)
+        .unwrap_or(false);
+    let Some(expected) = expected.filter(|_| size_matches) else {
+        return Ok(false);
+    };
+
+    let path = path.to_path_buf();
+    let actual = tokio::task::spawn_blocking(move || file_sha256(&path))
+        .await
+        .map_err(|e| GhExportError::Download(format!("Checksum task failed: {e}")))??;
+    if !actual.eq_ignore_ascii_case(&expected) {
+        warn!(
+            "Release asset {} does not match its checksum, downloading it again",
+            asset.name
+        );
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+fn file_sha256(path: &Path) -> Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        let read = file.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn load_checksums(path: &Path) -> Result<BTreeMap<String, String>> {
+    if path.exists() {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    } else {
+        Ok(BTreeMap::new())
+    }
+}
+
+// Sanitizing can map different tags to the same name (`v1/x` and `v1_x`), so
+// tags that had to be changed get the release id appended.
+fn release_dir_name(release: &Release) -> String {
+    let name = sanitize_file_name(&release.tag_name);
+    if name.is_empty() {
+        release.id.to_string()
+    } else if name != release.tag_name {
+        format!("{name}-{}", release.id)
+    } else {
+        name
+    }
+}
+
+fn asset_file_name(asset: &ReleaseAsset) -> String {
+    let name = sanitize_file_name(&asset.name);
+    if name.is_empty() {
+        format!("asset-{}", asset.id)
+    } else if name != asset.name {
+        format!("{name}-{}", asset.id)
+    } else {
+        name
+    }
+}
+
+fn sanitize_file_name(name: &str) -> String {
+    let sanitized: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || matches!(c, '.' | '-' | '_' | '+') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+
+    if sanitized.chars().all(|c| c == '.') {
+        String::new()
+    } else {
+        sanitized
+    }
+}
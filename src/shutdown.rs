@@ -0,0 +1,80 @@
+use console::style;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::sync::watch;
+use tracing::error;
+
+pub struct Shutdown {
+    requested: AtomicBool,
+    aborted: watch::Sender<bool>,
+}
+
+impl Shutdown {
+    pub fn install() -> Arc<Self> {
+        let shutdown = Arc::new(Self {
+            requested: AtomicBool::new(false),
+            aborted: watch::Sender::new(false),
+        });
+        tokio::spawn(listen(shutdown.clone()));
+        shutdown
+    }
+
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        *self.aborted.borrow()
+    }
+
+    // Resolves once a second signal asks for in-flight work to be abandoned.
+    pub async fn aborted(&self) {
+        let mut aborted = self.aborted.subscribe();
+        let _ = aborted.wait_for(|aborted| *aborted).await;
+    }
+}
+
+async fn listen(shutdown: Arc<Shutdown>) {
+    #[cfg(unix)]
+    let mut terminate =
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(signal) => Some(signal),
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                None
+            }
+        };
+
+    loop {
+        #[cfg(unix)]
+        let received = match terminate.as_mut() {
+            Some(terminate) => tokio::select! {
+                result = tokio::signal::ctrl_c() => result.is_ok(),
+                signal = terminate.recv() => signal.is_some(),
+            },
+            None => tokio::signal::ctrl_c().await.is_ok(),
+        };
+
+        #[cfg(not(unix))]
+        let received = tokio::signal::ctrl_c().await.is_ok();
+
+        if !received {
+            return;
+        }
+
+        if !shutdown.requested.swap(true, Ordering::SeqCst) {
+            eprintln!(
+                "\n{} Finishing in-flight repositories; no new ones will be started. Press Ctrl-C again to abort them.",
+                style("Interrupted.").yellow().bold()
+            );
+        } else if !shutdown.aborted.send_replace(true) {
+            eprintln!(
+                "\n{} Cancelling in-flight repositories.",
+                style("Aborting.").red().bold()
+            );
+        } else {
+            // Only reached if cancelling hangs; the state written so far is kept.
+            std::process::exit(130);
+        }
+    }
+}
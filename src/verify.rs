@@ -0,0 +1,287 @@
+use crate::error::{GhExportError, Result};
+use crate::github::GitHubClient;
+use git2::{ErrorCode, ObjectType, Oid, ReferenceType};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct LocalRepository {
+    pub full_name: String,
+    pub path: PathBuf,
+}
+
+impl LocalRepository {
+    fn is_wiki(&self) -> bool {
+        self.full_name.ends_with(".wiki")
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub corrupted: Option<String>,
+    pub stale: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+struct LocalState {
+    head_branch: Option<String>,
+    head_resolves: bool,
+    branches: HashMap<String, Oid>,
+}
+
+pub fn find_local_repositories(output_dir: &Path) -> Result<Vec<LocalRepository>> {
+    let mut repositories = Vec::new();
+
+    for owner_dir in fs::read_dir(output_dir)? {
+        let owner_dir = owner_dir?;
+        let owner = owner_dir.file_name().to_string_lossy().into_owned();
+        if owner.starts_with('.') || !owner_dir.file_type()?.is_dir() {
+            continue;
+        }
+
+        for entry in fs::read_dir(owner_dir.path())? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.starts_with('.') {
+                continue;
+            }
+
+            let path = entry.path();
+            if entry.file_type()?.is_dir()
+                && (path.join(".git").exists() || path.join("HEAD").is_file())
+            {
+                repositories.push(LocalRepository {
+                    full_name: format!("{owner}/{name}"),
+                    path,
+                });
+            }
+        }
+    }
+
+    repositories.sort_by(|a, b| a.full_name.cmp(&b.full_name));
+    Ok(repositories)
+}
+
+pub async fn verify_repository(
+    repo: &LocalRepository,
+    client: Option<&GitHubClient>,
+) -> Result<VerifyReport> {
+    let path = repo.path.clone();
+    let inspected = tokio::task::spawn_blocking(move || inspect_repository(&path))
+        .await
+        .map_err(|e| GhExportError::Download(format!("Verify task failed: {e}")))?;
+
+    let mut report = VerifyReport::default();
+    let local = match inspected {
+        Ok(local) => local,
+        Err(e) => {
+            report.corrupted = Some(e.message().to_string());
+            return Ok(report);
+        }
+    };
+
+    if let (Some(branch), false) = (&local.head_branch, local.head_resolves) {
+        report
+            .stale
+            .push(format!("HEAD points to missing branch {branch}"));
+    }
+
+    let Some(client) = client.filter(|_| !repo.is_wiki()) else {
+        return Ok(report);
+    };
+
+    let remote = match client.get_repository(&repo.full_name).await {
+        Ok(remote) => remote,
+        Err(GhExportError::NotFound(_)) => {
+            report.warnings.push("not found on GitHub".to_string());
+            return Ok(report);
+        }
+        Err(e) => {
+            report
+                .warnings
+                .push(format!("could not compare with GitHub: {e}"));
+            return Ok(report);
+        }
+    };
+
+    let branches = match client.list_branches(&remote.full_name).await {
+        Ok(branches) => branches,
+        Err(e) => {
+            report
+                .warnings
+                .push(format!("could not compare with GitHub: {e}"));
+            return Ok(report);
+        }
+    };
+
+    if !branches.is_empty() && local.head_branch.as_deref() != Some(&remote.default_branch) {
+        report.stale.push(format!(
+            "HEAD is on {} but the default branch is {}",
+            local.head_branch.as_deref().unwrap_or("a detached commit"),
+            remote.default_branch
+        ));
+    }
+
+    for branch in &branches {
+        match local.branches.get(&branch.name) {
+            None => report
+                .stale
+                .push(format!("branch {} is missing", branch.name)),
+            Some(oid) if oid.to_string() != branch.commit.sha => report.stale.push(format!(
+                "branch {} is at {}, GitHub has {}",
+                branch.name,
+                short_sha(&oid.to_string()),
+                short_sha(&branch.commit.sha)
+            )),
+            Some(_) => {}
+        }
+    }
+
+    Ok(report)
+}
+
+fn inspect_repository(path: &Path) -> std::result::Result<LocalState, git2::Error> {
+    let repo = git2::Repository::open(path)?;
+    let mut walker = ObjectWalker {
+        repo: &repo,
+        odb: repo.odb()?,
+        shallow: shallow_commits(&repo),
+        seen: HashSet::new(),
+    };
+
+    let mut local_branches = HashMap::new();
+    let mut remote_branches = HashMap::new();
+
+    for reference in repo.references()? {
+        let reference = reference?;
+        if reference.kind() == Some(ReferenceType::Symbolic) {
+            continue;
+        }
+
+        let name = reference.name().unwrap_or_default().to_string();
+        let oid = reference
+            .target()
+            .ok_or_else(|| git2::Error::from_str(&format!("reference {name} has no target")))?;
+        walker
+            .walk(oid)
+            .map_err(|e| git2::Error::from_str(&format!("{name}: {}", e.message())))?;
+
+        if let Some(branch) = name.strip_prefix("refs/heads/") {
+            local_branches.insert(branch.to_string(), oid);
+        } else if let Some(branch) = name.strip_prefix("refs/remotes/origin/") {
+            remote_branches.insert(branch.to_string(), oid);
+        }
+    }
+
+    let (head_branch, head_resolves) = match repo.head() {
+        Ok(head) => {
+            head.peel_to_commit().map_err(|e| {
+                git2::Error::from_str(&format!("HEAD does not resolve: {}", e.message()))
+            })?;
+            (
+                head.shorthand()
+                    .filter(|_| head.is_branch())
+                    .map(str::to_string),
+                true,
+            )
+        }
+        Err(e) if e.code() == ErrorCode::UnbornBranch => {
+            let target = repo
+                .find_reference("HEAD")?
+                .symbolic_target()
+                .map(|target| {
+                    target
+                        .strip_prefix("refs/heads/")
+                        .unwrap_or(target)
+                        .to_string()
+                });
+            // An empty repository has an unborn HEAD and nothing else.
+            (target, local_branches.is_empty())
+        }
+        Err(e) => {
+            return Err(git2::Error::from_str(&format!(
+                "HEAD does not resolve: {}",
+                e.message()
+            )))
+        }
+    };
+
+    // Working copies track GitHub through origin's remote-tracking refs; local
+    // branches can lag behind when they have diverged.
+    local_branches.extend(remote_branches);
+
+    Ok(LocalState {
+        head_branch,
+        head_resolves,
+        branches: local_branches,
+    })
+}
+
+struct ObjectWalker<'a> {
+    repo: &'a git2::Repository,
+    odb: git2::Odb<'a>,
+    shallow: HashSet<Oid>,
+    seen: HashSet<Oid>,
+}
+
+impl ObjectWalker<'_> {
+    fn walk(&mut self, start: Oid) -> std::result::Result<(), git2::Error> {
+        let mut pending = vec![start];
+
+        while let Some(oid) = pending.pop() {
+            if !self.seen.insert(oid) {
+                continue;
+            }
+
+            // Reading the object checks that it exists and that its content
+            // still hashes to its id.
+            let kind = self
+                .odb
+                .read(oid)
+                .map_err(|e| {
+                    git2::Error::from_str(&format!(
+                        "missing or corrupt object {oid}: {}",
+                        e.message()
+                    ))
+                })?
+                .kind();
+
+            match kind {
+                ObjectType::Commit => {
+                    let commit = self.repo.find_commit(oid)?;
+                    pending.push(commit.tree_id());
+                    if !self.shallow.contains(&oid) {
+                        pending.extend(commit.parent_ids());
+                    }
+                }
+                ObjectType::Tree => {
+                    let tree = self.repo.find_tree(oid)?;
+                    pending.extend(
+                        tree.iter()
+                            .filter(|entry| entry.kind() != Some(ObjectType::Commit))
+                            .map(|entry| entry.id()),
+                    );
+                }
+                ObjectType::Tag => {
+                    pending.push(self.repo.find_tag(oid)?.target_id());
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn shallow_commits(repo: &git2::Repository) -> HashSet<Oid> {
+    fs::read_to_string(repo.path().join("shallow"))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| Oid::from_str(line.trim()).ok())
+        .collect()
+}
+
+fn short_sha(sha: &str) -> &str {
+    sha.get(..7).unwrap_or(sha)
+}
@@ -1,3 +1,6 @@
+// The original tests pass `&[...]` to `Command::args`.
+#![allow(clippy::needless_borrows_for_generic_args)]
+
 use std::process::Command;
 
 #[test]
@@ -37,3 +40,1753 @@ fn test_config_show_command() {
     let stdout = String::from_utf8_lossy(&output.stdout);
     assert!(stdout.contains("Current configuration"));
 }
+
+#[test]
+fn test_custom_api_url_is_used() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/user")
+        .with_status(401)
+        .with_body(r#"{"message":"Bad credentials"}"#)
+        .create();
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let output = gh_export()
+        .args(["--token", "invalid", "--api-url", &server.url()])
+        .arg("--output")
+        .arg(output_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(5));
+    mock.assert();
+}
+
+#[test]
+fn test_sync_rejects_invalid_since_date() {
+    let output = gh_export()
+        .args(["--token", "invalid", "sync", "--since", "yesterday"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Invalid --since value"));
+}
+
+#[test]
+fn test_sync_since_only_updates_repositories_modified_after_the_date() {
+    let (_source_dir, clone_url) = create_source_repository();
+    let output_dir = tempfile::tempdir().unwrap();
+    let pushed_at = |name: &str, date: &str| {
+        repository_with_clone_url(1 + name.len() as u64, "octocat", name, clone_url.as_str())
+            .replace(r#""pushed_at":null"#, &format!(r#""pushed_at":"{date}""#))
+    };
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(
+        &mut server,
+        &[
+            pushed_at("old", "2024-01-01T00:00:00Z"),
+            pushed_at("recent", "2024-07-01T00:00:00Z"),
+        ],
+    );
+
+    let output = export_command(&server, output_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+
+    let output = export_command(&server, output_dir.path())
+        .args(["--output-format", "json", "sync", "--since", "2024-06-01"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let events: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events[0]["event"], "discovery");
+    assert_eq!(events[0]["discovered"], 2);
+    assert_eq!(events[0]["selected"], 1);
+    let started: Vec<_> = events
+        .iter()
+        .filter(|event| event["event"] == "repo_started")
+        .map(|event| event["repository"].as_str().unwrap())
+        .collect();
+    assert_eq!(started, ["octocat/recent"]);
+}
+
+#[test]
+fn test_sync_still_exports_releases_of_unchanged_repositories() {
+    let (_source_dir, clone_url) = create_source_repository();
+    let output_dir = tempfile::tempdir().unwrap();
+    git2::Repository::clone(clone_url.as_str(), output_dir.path().join("octocat/hello")).unwrap();
+    // Fetching from here would fail, so the run only succeeds without one.
+    let missing_dir = tempfile::tempdir().unwrap();
+    let missing_url = url::Url::from_file_path(missing_dir.path().join("missing.git")).unwrap();
+
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(
+        &mut server,
+        &[repository_with_clone_url(
+            1,
+            "octocat",
+            "hello",
+            missing_url.as_str(),
+        )],
+    );
+    let releases = mock_json(
+        &mut server,
+        "/repos/octocat/hello/releases",
+        mockito::Matcher::Any,
+        "[]",
+    );
+
+    let output = export_command(&server, output_dir.path())
+        .args(["--with-releases", "sync", "--since", "2024-06-01"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    releases.assert();
+}
+
+#[test]
+fn test_organization_repositories_are_exported_once_under_the_organization() {
+    let (_source_dir, clone_url) = create_source_repository();
+    let output_dir = tempfile::tempdir().unwrap();
+    let shared = repository_with_clone_url(2, "acme", "shared", clone_url.as_str());
+    let mut server = mockito::Server::new();
+    // The authenticated user's listing also includes repositories they
+    // collaborate on.
+    let _mocks = mock_github(
+        &mut server,
+        &[
+            repository_with_clone_url(1, "octocat", "hello", clone_url.as_str()),
+            shared.clone(),
+        ],
+    );
+    let orgs = server
+        .mock("GET", "/user/orgs")
+        .match_query(mockito::Matcher::Any)
+        .with_status(200)
+        .with_body(r#"[{"login":"acme","id":10},{"login":"tools","id":11}]"#)
+        .create();
+    let acme = server
+        .mock("GET", "/orgs/acme/repos")
+        .match_query(mockito::Matcher::Any)
+        .with_status(200)
+        .with_body(format!(
+            "[{shared},{}]",
+            repository_with_clone_url(3, "acme", "api", clone_url.as_str())
+        ))
+        .expect(1)
+        .create();
+    let tools = server
+        .mock("GET", "/orgs/tools/repos")
+        .match_query(mockito::Matcher::Any)
+        .with_status(200)
+        .with_body("[]")
+        .create();
+
+    let output = export_command(&server, output_dir.path())
+        .args(["--org", "acme", "--all-orgs", "--output-format", "json"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    orgs.assert();
+    acme.assert();
+    tools.assert();
+
+    let mut started: Vec<String> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .filter(|event| event["event"] == "repo_started")
+        .map(|event| event["repository"].as_str().unwrap().to_string())
+        .collect();
+    started.sort();
+    assert_eq!(started, ["acme/api", "acme/shared", "octocat/hello"]);
+    assert!(output_dir.path().join("acme/api/.git").exists());
+    assert!(output_dir.path().join("acme/shared/.git").exists());
+    assert!(output_dir.path().join("octocat/hello/.git").exists());
+}
+
+#[test]
+fn test_non_interactive_without_token_fails_with_config_exit_code() {
+    let config_home = tempfile::tempdir().unwrap();
+
+    let output = Command::new("cargo")
+        .args(["run", "--", "--non-interactive"])
+        .env_remove("GITHUB_TOKEN")
+        .env("HOME", config_home.path())
+        .env("XDG_CONFIG_HOME", config_home.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(78));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("No GitHub token configured"));
+}
+
+#[test]
+fn test_first_export_without_terminal_requires_yes() {
+    let (_source_dir, clone_url) = create_source_repository();
+    let output_dir = tempfile::tempdir().unwrap();
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(
+        &mut server,
+        &[repository_with_clone_url(
+            1,
+            "octocat",
+            "hello",
+            clone_url.as_str(),
+        )],
+    );
+
+    let output = gh_export()
+        .args(["--token", "invalid", "--api-url", &server.url()])
+        .arg("--output")
+        .arg(output_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(78));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Pass --yes"));
+    assert!(!output_dir.path().join("octocat/hello").exists());
+}
+
+#[test]
+fn test_rate_limit_exceeding_wait_budget_fails() {
+    let mut server = mockito::Server::new();
+    let reset = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+        + 3600;
+    let mock = server
+        .mock("GET", "/user")
+        .with_status(403)
+        .with_header("x-ratelimit-remaining", "0")
+        .with_header("x-ratelimit-reset", &reset.to_string())
+        .create();
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let output = gh_export()
+        .args(["--token", "invalid", "--api-url", &server.url()])
+        .args(["--max-rate-limit-wait", "0", "--output"])
+        .arg(output_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(6));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Rate limit exceeded"));
+    mock.assert();
+}
+
+#[test]
+fn test_rate_limit_without_reset_time_is_not_retried_immediately() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/user")
+        .with_status(403)
+        .with_header("x-ratelimit-remaining", "0")
+        .expect(1)
+        .create();
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let output = gh_export()
+        .args(["--token", "invalid", "--api-url", &server.url()])
+        .args(["--max-rate-limit-wait", "30", "--output"])
+        .arg(output_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(6));
+    mock.assert();
+}
+
+#[test]
+fn test_secondary_rate_limit_in_body_is_treated_as_rate_limit() {
+    let mut server = mockito::Server::new();
+    let mock = server
+        .mock("GET", "/user")
+        .with_status(403)
+        .with_header("x-ratelimit-remaining", "4000")
+        .with_body(r#"{"message":"You have exceeded a secondary rate limit. Please wait a few minutes before you try again."}"#)
+        .create();
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let output = gh_export()
+        .args(["--token", "invalid", "--api-url", &server.url()])
+        .args(["--max-rate-limit-wait", "0", "--output"])
+        .arg(output_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(6));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("secondary rate limit"));
+    mock.assert();
+}
+
+#[test]
+fn test_repository_listing_follows_link_header() {
+    let mut server = mockito::Server::new();
+    let _account = mock_account(&mut server);
+    let first_page = server
+        .mock("GET", "/users/octocat/repos")
+        .match_query(mockito::Matcher::Exact("per_page=100".to_string()))
+        .with_status(200)
+        .with_header(
+            "link",
+            &format!(
+                r#"<{}/users/octocat/repos?per_page=100&page=2>; rel="next""#,
+                server.url()
+            ),
+        )
+        .with_body("[]")
+        .create();
+    let second_page = server
+        .mock("GET", "/users/octocat/repos")
+        .match_query(mockito::Matcher::Exact("per_page=100&page=2".to_string()))
+        .with_status(200)
+        .with_body("[]")
+        .create();
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let output = gh_export()
+        .args(["--token", "invalid", "--api-url", &server.url()])
+        .arg("--output")
+        .arg(output_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("No repositories to export"));
+    first_page.assert();
+    second_page.assert();
+}
+
+#[test]
+fn test_conditional_requests_use_cached_etag() {
+    let mut server = mockito::Server::new();
+    let _account = mock_account(&mut server);
+    let fresh = server
+        .mock("GET", "/users/octocat/repos")
+        .match_query(mockito::Matcher::Any)
+        .match_header("if-none-match", mockito::Matcher::Missing)
+        .with_status(200)
+        .with_header("etag", r#""repos-v1""#)
+        .with_body("[]")
+        .expect(1)
+        .create();
+    let not_modified = server
+        .mock("GET", "/users/octocat/repos")
+        .match_query(mockito::Matcher::Any)
+        .match_header("if-none-match", r#""repos-v1""#)
+        .with_status(304)
+        .expect(1)
+        .create();
+    let output_dir = tempfile::tempdir().unwrap();
+
+    for _ in 0..2 {
+        let output = gh_export()
+            .args(["--token", "invalid", "--api-url", &server.url()])
+            .arg("--output")
+            .arg(output_dir.path())
+            .output()
+            .expect("Failed to execute command");
+
+        assert!(output.status.success());
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.contains("No repositories to export"));
+    }
+
+    fresh.assert();
+    not_modified.assert();
+}
+
+#[test]
+fn test_verify_reports_branches_behind_github() {
+    let output_dir = tempfile::tempdir().unwrap();
+    let repo = git2::Repository::init(output_dir.path().join("octocat/hello")).unwrap();
+    let signature = git2::Signature::now("Octocat", "octocat@example.com").unwrap();
+    let tree = repo
+        .find_tree(repo.index().unwrap().write_tree().unwrap())
+        .unwrap();
+    repo.commit(Some("HEAD"), &signature, &signature, "Initial", &tree, &[])
+        .unwrap();
+    let head = repo.head().unwrap().shorthand().unwrap().to_string();
+
+    let mut server = mockito::Server::new();
+    let _repository = server
+        .mock("GET", "/repos/octocat/hello")
+        .with_status(200)
+        .with_body(repository_json(1, "octocat", "hello", &head))
+        .create();
+    let _branches = server
+        .mock("GET", "/repos/octocat/hello/branches")
+        .match_query(mockito::Matcher::Any)
+        .with_status(200)
+        .with_body(format!(
+            r#"[{{"name":"{head}","commit":{{"sha":"{}"}}}}]"#,
+            "a".repeat(40)
+        ))
+        .create();
+
+    let output = gh_export()
+        .args(["--token", "invalid", "--api-url", &server.url()])
+        .arg("--output")
+        .arg(output_dir.path())
+        .arg("verify")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(8));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("octocat/hello is out of date"));
+    assert!(stdout.contains("GitHub has aaaaaaa"));
+    assert!(stdout.contains("Corrupted: 0"));
+
+    let output = gh_export()
+        .args(["--token", "invalid", "--api-url", &server.url()])
+        .args(["--output-format", "json", "--output"])
+        .arg(output_dir.path())
+        .arg("verify")
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(8));
+    let events: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0]["event"], "verified");
+    assert_eq!(events[0]["repository"], "octocat/hello");
+    assert_eq!(events[0]["result"], "stale");
+    assert!(events[0]["stale"][0]
+        .as_str()
+        .unwrap()
+        .contains("GitHub has aaaaaaa"));
+    assert_eq!(events[1]["event"], "verify_summary");
+    assert_eq!(events[1]["stale"], 1);
+    assert_eq!(events[1]["corrupted"], 0);
+}
+
+#[test]
+fn test_renamed_and_deleted_repositories_are_moved() {
+    let (_source_dir, clone_url) = create_source_repository();
+    let output_dir = tempfile::tempdir().unwrap();
+    git2::Repository::clone(
+        clone_url.as_str(),
+        output_dir.path().join("octocat/old-name"),
+    )
+    .unwrap();
+    std::fs::create_dir_all(output_dir.path().join("octocat/deleted/.git")).unwrap();
+    std::fs::write(
+        output_dir.path().join(".gh-export-manifest.json"),
+        r#"{"repositories":{"1":{"full_name":"octocat/old-name"},"2":{"full_name":"octocat/deleted"}}}"#,
+    )
+    .unwrap();
+
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(
+        &mut server,
+        &[repository_with_clone_url(
+            1,
+            "octocat",
+            "new-name",
+            clone_url.as_str(),
+        )],
+    );
+    let _deleted = server
+        .mock("GET", "/repositories/2")
+        .with_status(404)
+        .create();
+
+    let export = || {
+        export_command(&server, output_dir.path())
+            .output()
+            .expect("Failed to execute command")
+    };
+    let manifest = || -> serde_json::Value {
+        serde_json::from_str(
+            &std::fs::read_to_string(output_dir.path().join(".gh-export-manifest.json")).unwrap(),
+        )
+        .unwrap()
+    };
+
+    let output = export();
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("octocat/old-name → octocat/new-name"));
+    assert!(output_dir.path().join("octocat/new-name/.git").exists());
+    assert!(!output_dir.path().join("octocat/old-name").exists());
+    // A single 404 may only mean the token lost access, so nothing moves yet.
+    assert!(stdout.contains("octocat/deleted was not found on GitHub"));
+    assert!(output_dir.path().join("octocat/deleted").exists());
+    assert!(manifest()["repositories"]["2"]["missing_since"].is_string());
+
+    assert_eq!(export().status.code(), Some(0));
+    assert!(output_dir.path().join("_orphaned/octocat/deleted").exists());
+    assert!(!output_dir.path().join("octocat/deleted").exists());
+
+    let manifest = manifest();
+    let entry = &manifest["repositories"]["1"];
+    assert_eq!(entry["full_name"], "octocat/new-name");
+    assert_eq!(entry["failed_attempts"], 0);
+    assert!(entry["last_error"].is_null());
+    assert_eq!(manifest["orphaned"][0]["full_name"], "octocat/deleted");
+}
+
+#[test]
+fn test_failed_attempts_are_counted_until_an_export_succeeds() {
+    let (_source_dir, clone_url) = create_source_repository();
+    let missing_dir = tempfile::tempdir().unwrap();
+    let missing_url = url::Url::from_file_path(missing_dir.path().join("missing.git")).unwrap();
+    let output_dir = tempfile::tempdir().unwrap();
+    let manifest = || -> serde_json::Value {
+        serde_json::from_str(
+            &std::fs::read_to_string(output_dir.path().join(".gh-export-manifest.json")).unwrap(),
+        )
+        .unwrap()
+    };
+    let export = |url: &str| {
+        let mut server = mockito::Server::new();
+        let _mocks = mock_github(
+            &mut server,
+            &[repository_with_clone_url(1, "octocat", "flaky", url)],
+        );
+        export_command(&server, output_dir.path())
+            .output()
+            .expect("Failed to execute command")
+    };
+
+    assert_eq!(export(missing_url.as_str()).status.code(), Some(4));
+    assert_eq!(export(missing_url.as_str()).status.code(), Some(4));
+    let entry = &manifest()["repositories"]["1"];
+    assert_eq!(entry["failed_attempts"], 2);
+    assert!(entry["last_error"].is_string());
+
+    assert_eq!(export(clone_url.as_str()).status.code(), Some(0));
+    let entry = &manifest()["repositories"]["1"];
+    assert_eq!(entry["failed_attempts"], 0);
+    assert!(entry["last_error"].is_null());
+}
+
+fn repository_json(id: u64, owner: &str, name: &str, default_branch: &str) -> String {
+    format!(
+        r#"{{"id":{id},"name":"{name}","full_name":"{owner}/{name}",
+            "owner":{{"login":"{owner}","id":1,"type":"User"}},
+            "private":false,"html_url":"","description":null,"fork":false,
+            "created_at":"","updated_at":"","pushed_at":null,
+            "clone_url":"","ssh_url":"","size":1,"stargazers_count":0,
+            "watchers_count":0,"language":null,"archived":false,
+            "disabled":false,"default_branch":"{default_branch}"}}"#
+    )
+}
+
+#[test]
+fn test_retry_failed_only_exports_failed_repositories() {
+    let (_source_dir, clone_url) = create_source_repository();
+
+    let output_dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(output_dir.path().join("octocat")).unwrap();
+    std::fs::write(
+        output_dir.path().join("octocat/.gh-export-metadata.json"),
+        r#"{"last_export":"2024-01-01T00:00:00Z","total_repos":2,"successful_exports":1,
+            "failed_exports":["octocat/flaky"],"export_duration_seconds":10}"#,
+    )
+    .unwrap();
+
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(
+        &mut server,
+        &[
+            repository_with_clone_url(1, "octocat", "flaky", clone_url.as_str()),
+            repository_with_clone_url(2, "octocat", "stable", clone_url.as_str()),
+        ],
+    );
+
+    let output = gh_export()
+        .args(["--token", "invalid", "--api-url", &server.url()])
+        .arg("--output")
+        .arg(output_dir.path())
+        .arg("retry-failed")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    assert!(output_dir.path().join("octocat/flaky/.git").exists());
+    assert!(!output_dir.path().join("octocat/stable").exists());
+
+    let metadata =
+        std::fs::read_to_string(output_dir.path().join("octocat/.gh-export-metadata.json"))
+            .unwrap();
+    let metadata: serde_json::Value = serde_json::from_str(&metadata).unwrap();
+    assert_eq!(metadata["failed_exports"], serde_json::json!([]));
+    assert_eq!(metadata["successful_exports"], 2);
+}
+
+#[test]
+fn test_partial_failure_exits_with_distinct_code() {
+    let (_source_dir, clone_url) = create_source_repository();
+    let missing_dir = tempfile::tempdir().unwrap();
+    let missing_url = url::Url::from_file_path(missing_dir.path().join("missing.git")).unwrap();
+
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(
+        &mut server,
+        &[
+            repository_with_clone_url(1, "octocat", "good", clone_url.as_str()),
+            repository_with_clone_url(2, "octocat", "broken", missing_url.as_str()),
+        ],
+    );
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let output = export_command(&server, output_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(3));
+    assert!(output_dir.path().join("octocat/good/.git").exists());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("1 repositories failed to export (1 succeeded)"));
+}
+
+#[test]
+fn test_stale_staging_is_removed_without_touching_backups() {
+    let (_source_dir, clone_url) = create_source_repository();
+    let output_dir = tempfile::tempdir().unwrap();
+    let staging = output_dir.path().join(".gh-export-staging/octocat/fresh");
+    std::fs::create_dir_all(staging.join(".git")).unwrap();
+    // A repository that happens to be named like an old-style staging dir.
+    let lookalike = output_dir.path().join("octocat/tool.partial");
+    git2::Repository::init(&lookalike).unwrap();
+
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(
+        &mut server,
+        &[repository_with_clone_url(
+            1,
+            "octocat",
+            "fresh",
+            clone_url.as_str(),
+        )],
+    );
+
+    let output = export_command(&server, output_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Cleaned up 1 interrupted clone(s)"));
+    assert!(!output_dir.path().join(".gh-export-staging").exists());
+    assert!(lookalike.join(".git").exists());
+    assert!(output_dir.path().join("octocat/fresh/.git").exists());
+}
+
+// Starts an export in which `pending` never finishes, and kills it once every
+// repository in `completed` has been recorded in the journal.
+fn crash_export(output_dir: &std::path::Path, clone_url: &str, completed: &[&str], pending: &str) {
+    let hanging = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let hanging_url = format!("http://{}/{pending}.git", hanging.local_addr().unwrap());
+    let mut repos: Vec<_> = completed
+        .iter()
+        .enumerate()
+        .map(|(id, name)| repository_with_clone_url(id as u64 + 1, "octocat", name, clone_url))
+        .collect();
+    repos.push(repository_with_clone_url(
+        100,
+        "octocat",
+        pending,
+        &hanging_url,
+    ));
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(&mut server, &repos);
+    let home = tempfile::tempdir().unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gh-export"))
+        .args(["--yes", "--token", "invalid", "--api-url", &server.url()])
+        .arg("--output")
+        .arg(output_dir)
+        .env("HOME", home.path())
+        .env("XDG_CONFIG_HOME", home.path())
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .expect("Failed to execute command");
+
+    let journal = output_dir.join(".gh-export-journal.jsonl");
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(60);
+    while !std::fs::read_to_string(&journal).is_ok_and(|journal| {
+        completed
+            .iter()
+            .all(|name| journal.contains(&format!("octocat/{name}")))
+    }) {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "export never finished"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    }
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn test_killed_export_resumes_from_journal() {
+    let (_source_dir, clone_url) = create_source_repository();
+    let output_dir = tempfile::tempdir().unwrap();
+    crash_export(
+        output_dir.path(),
+        clone_url.as_str(),
+        &["done", "removed"],
+        "pending",
+    );
+    std::fs::remove_dir_all(output_dir.path().join("octocat/removed")).unwrap();
+
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(
+        &mut server,
+        &[
+            repository_with_clone_url(1, "octocat", "done", clone_url.as_str()),
+            repository_with_clone_url(2, "octocat", "removed", clone_url.as_str()),
+            repository_with_clone_url(100, "octocat", "pending", clone_url.as_str()),
+        ],
+    );
+
+    let output = export_command(&server, output_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Resuming"));
+    assert!(stdout.contains("Already done before interruption: 1"));
+    assert!(output_dir.path().join("octocat/pending/.git").exists());
+    // A journal entry whose directory has gone is exported again.
+    assert!(output_dir.path().join("octocat/removed/.git").exists());
+    // A run that finishes removes the journal, so the next run starts over.
+    assert!(!output_dir.path().join(".gh-export-journal.jsonl").exists());
+}
+
+#[test]
+fn test_resumed_repositories_are_reported_as_events() {
+    let (_source_dir, clone_url) = create_source_repository();
+    let output_dir = tempfile::tempdir().unwrap();
+    crash_export(output_dir.path(), clone_url.as_str(), &["done"], "pending");
+
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(
+        &mut server,
+        &[
+            repository_with_clone_url(1, "octocat", "done", clone_url.as_str()),
+            repository_with_clone_url(100, "octocat", "pending", clone_url.as_str()),
+        ],
+    );
+
+    let output = export_command(&server, output_dir.path())
+        .args(["--output-format", "json"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let finished: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .filter(|event| event["event"] == "repo_finished")
+        .collect();
+    assert_eq!(finished.len(), 2);
+    let done = finished
+        .iter()
+        .find(|event| event["repository"] == "octocat/done")
+        .unwrap();
+    assert_eq!(done["result"], "skipped");
+    assert_eq!(done["reason"], "completed before interruption");
+}
+
+#[test]
+fn test_journal_of_a_different_selection_is_not_resumed() {
+    let (_source_dir, clone_url) = create_source_repository();
+    let output_dir = tempfile::tempdir().unwrap();
+    crash_export(output_dir.path(), clone_url.as_str(), &["done"], "pending");
+
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(
+        &mut server,
+        &[
+            repository_with_clone_url(1, "octocat", "done", clone_url.as_str()),
+            repository_with_clone_url(100, "octocat", "pending", clone_url.as_str()),
+            repository_with_clone_url(101, "octocat", "new", clone_url.as_str()),
+        ],
+    );
+
+    let output = export_command(&server, output_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.contains("Resuming"));
+    assert!(!stdout.contains("Already done before interruption"));
+}
+
+#[test]
+fn test_token_rejected_for_every_clone_exits_with_auth_code() {
+    let output_dir = tempfile::tempdir().unwrap();
+    let mut server = mockito::Server::new();
+    let clone_url = format!("{}/octocat/hello.git", server.url());
+    let _mocks = mock_github(
+        &mut server,
+        &[repository_with_clone_url(1, "octocat", "hello", &clone_url)],
+    );
+    let _refs = server
+        .mock("GET", "/octocat/hello.git/info/refs")
+        .match_query(mockito::Matcher::Any)
+        .with_status(401)
+        .with_header("WWW-Authenticate", r#"Basic realm="GitHub""#)
+        .create();
+
+    let output = export_command(&server, output_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(
+        output.status.code(),
+        Some(5),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_total_failure_ignores_repositories_that_were_not_attempted() {
+    let (_source_dir, clone_url) = create_source_repository();
+    let missing_dir = tempfile::tempdir().unwrap();
+    let missing_url = url::Url::from_file_path(missing_dir.path().join("missing.git")).unwrap();
+    let output_dir = tempfile::tempdir().unwrap();
+    crash_export(output_dir.path(), clone_url.as_str(), &["done"], "broken");
+
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(
+        &mut server,
+        &[
+            repository_with_clone_url(1, "octocat", "done", missing_url.as_str()),
+            repository_with_clone_url(100, "octocat", "broken", missing_url.as_str()),
+        ],
+    );
+
+    let output = export_command(&server, output_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert_eq!(output.status.code(), Some(4));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Already done before interruption: 1"));
+}
+
+#[test]
+fn test_list_previews_export_without_touching_disk() {
+    let output_dir = tempfile::tempdir().unwrap();
+    std::fs::create_dir_all(output_dir.path().join("octocat/existing/.git")).unwrap();
+
+    let mut server = mockito::Server::new();
+    let archived = repository_json(3, "octocat", "archived", "main")
+        .replace(r#""archived":false"#, r#""archived":true"#);
+    let _mocks = mock_github(
+        &mut server,
+        &[
+            repository_json(1, "octocat", "new", "main"),
+            repository_json(2, "octocat", "existing", "main"),
+            archived,
+        ],
+    );
+
+    let output = gh_export()
+        .args(["--token", "invalid", "--api-url", &server.url()])
+        .arg("--output")
+        .arg(output_dir.path())
+        .args(["list", "--format", "json"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let plan: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let actions: Vec<_> = plan
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            (
+                entry["repository"].as_str().unwrap(),
+                entry["action"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        actions,
+        [
+            ("octocat/new", "clone"),
+            ("octocat/existing", "update"),
+            ("octocat/archived", "skip"),
+        ]
+    );
+
+    let entries: Vec<_> = std::fs::read_dir(output_dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect();
+    assert_eq!(entries, ["octocat"]);
+}
+
+#[test]
+fn test_json_output_emits_events() {
+    let (_source_dir, clone_url) = create_source_repository();
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(
+        &mut server,
+        &[repository_with_clone_url(
+            1,
+            "octocat",
+            "hello",
+            clone_url.as_str(),
+        )],
+    );
+    std::fs::create_dir_all(output_dir.path().join("octocat")).unwrap();
+
+    let output = gh_export()
+        .args([
+            "--token",
+            "invalid",
+            "--api-url",
+            &server.url(),
+            "--output-format",
+            "json",
+        ])
+        .arg("--output")
+        .arg(output_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let events: Vec<serde_json::Value> = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let names: Vec<_> = events
+        .iter()
+        .map(|event| event["event"].as_str().unwrap())
+        .filter(|name| *name != "progress")
+        .collect();
+    assert_eq!(
+        names,
+        ["discovery", "repo_started", "repo_finished", "summary"]
+    );
+    assert!(events
+        .iter()
+        .filter_map(|event| event.get("repository"))
+        .all(|repository| repository == "octocat/hello"));
+    assert_eq!(events[events.len() - 2]["result"], "success");
+    assert_eq!(events[events.len() - 1]["successful"], 1);
+
+    let output = gh_export()
+        .args(["--output-format", "json", "--output"])
+        .arg(output_dir.path())
+        .arg("status")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let metadata: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(metadata["exported"], true);
+    assert_eq!(metadata["total_repos"], 1);
+}
+
+#[test]
+fn test_json_status_without_an_export() {
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let output = gh_export()
+        .args(["--output-format", "json", "--output"])
+        .arg(output_dir.path())
+        .arg("status")
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let status: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(status, serde_json::json!({ "exported": false }));
+}
+
+fn create_source_repository() -> (tempfile::TempDir, url::Url) {
+    let source_dir = tempfile::tempdir().unwrap();
+    init_source_repository(source_dir.path());
+    let clone_url = url::Url::from_directory_path(source_dir.path()).unwrap();
+    (source_dir, clone_url)
+}
+
+fn init_source_repository(path: &std::path::Path) {
+    let source = git2::Repository::init_bare(path).unwrap();
+    let signature = git2::Signature::now("Octocat", "octocat@example.com").unwrap();
+    let tree = source
+        .find_tree(source.treebuilder(None).unwrap().write().unwrap())
+        .unwrap();
+    source
+        .commit(
+            Some("refs/heads/main"),
+            &signature,
+            &signature,
+            "Initial",
+            &tree,
+            &[],
+        )
+        .unwrap();
+    source.set_head("refs/heads/main").unwrap();
+}
+
+// Commits `path` with `content` on top of `branch` without touching any
+// working tree, so it works for bare sources and local clones alike.
+fn commit_file(repo: &git2::Repository, branch: &str, path: &str, content: &str) -> git2::Oid {
+    let reference = format!("refs/heads/{branch}");
+    let parent = repo
+        .find_reference(&reference)
+        .and_then(|reference| reference.peel_to_commit())
+        .unwrap();
+    let mut tree = repo.treebuilder(Some(&parent.tree().unwrap())).unwrap();
+    let blob = repo.blob(content.as_bytes()).unwrap();
+    tree.insert(path, blob, 0o100644).unwrap();
+    let tree = repo.find_tree(tree.write().unwrap()).unwrap();
+    let signature = git2::Signature::now("Octocat", "octocat@example.com").unwrap();
+    repo.commit(
+        Some(&reference),
+        &signature,
+        &signature,
+        &format!("Update {path}"),
+        &tree,
+        &[&parent],
+    )
+    .unwrap()
+}
+
+fn develop_source_repository() -> (tempfile::TempDir, String) {
+    let (source_dir, clone_url) = create_source_repository();
+    let source = git2::Repository::open_bare(source_dir.path()).unwrap();
+    let main = source.head().unwrap().peel_to_commit().unwrap();
+    source.branch("develop", &main, false).unwrap();
+    commit_file(&source, "develop", "README.md", "hello\n");
+    source.set_head("refs/heads/develop").unwrap();
+
+    let repo = repository_json(1, "octocat", "hello", "develop").replace(
+        r#""clone_url":"""#,
+        &format!(r#""clone_url":"{clone_url}""#),
+    );
+    (source_dir, repo)
+}
+
+#[test]
+fn test_diverged_default_branch_is_reported() {
+    let (source_dir, repo) = develop_source_repository();
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(&mut server, &[repo]);
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let output = export_command(&server, output_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+
+    let local_path = output_dir.path().join("octocat/hello");
+    let local = git2::Repository::open(&local_path).unwrap();
+    assert_eq!(local.head().unwrap().shorthand(), Some("develop"));
+    let local_commit = commit_file(&local, "develop", "local.txt", "local\n");
+    local
+        .checkout_head(Some(git2::build::CheckoutBuilder::default().force()))
+        .unwrap();
+    let source = git2::Repository::open_bare(source_dir.path()).unwrap();
+    commit_file(&source, "develop", "upstream.txt", "upstream\n");
+
+    let output = export_command(&server, output_dir.path())
+        .args(["--output-format", "json"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let finished = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .find(|event| event["event"] == "repo_finished")
+        .unwrap();
+    assert_eq!(
+        finished["diverged_branches"],
+        serde_json::json!(["develop"])
+    );
+    let local = git2::Repository::open(&local_path).unwrap();
+    assert_eq!(local.head().unwrap().target(), Some(local_commit));
+}
+
+#[test]
+fn test_update_keeps_uncommitted_changes() {
+    let (source_dir, repo) = develop_source_repository();
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(&mut server, &[repo]);
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let output = export_command(&server, output_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+
+    let readme = output_dir.path().join("octocat/hello/README.md");
+    std::fs::write(&readme, "edited locally\n").unwrap();
+    let source = git2::Repository::open_bare(source_dir.path()).unwrap();
+    commit_file(&source, "develop", "README.md", "changed upstream\n");
+
+    let output = export_command(&server, output_dir.path())
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    assert_eq!(
+        std::fs::read_to_string(&readme).unwrap(),
+        "edited locally\n"
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("has uncommitted changes"));
+}
+
+fn mock_json(
+    server: &mut mockito::Server,
+    path: &str,
+    query: mockito::Matcher,
+    body: &str,
+) -> mockito::Mock {
+    server
+        .mock("GET", path)
+        .match_query(query)
+        .with_status(200)
+        .with_body(body)
+        .create()
+}
+
+#[test]
+fn test_issues_are_exported_incrementally() {
+    use mockito::Matcher;
+
+    let (_source_dir, clone_url) = create_source_repository();
+    let repo = repository_with_clone_url(1, "octocat", "hello", clone_url.as_str()).replace(
+        r#""archived":false"#,
+        r#""archived":false,"has_issues":true"#,
+    );
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(&mut server, &[repo]);
+    let output_dir = tempfile::tempdir().unwrap();
+    let pulls_page_2 = format!("{}/repos/octocat/hello/pulls?page=2", server.url());
+    let old_pull = r#"[{"number":3,"title":"Old","updated_at":"2000-01-01T00:00:00Z"}]"#;
+
+    let first_run = vec![
+        mock_json(
+            &mut server,
+            "/repos/octocat/hello/issues",
+            Matcher::Any,
+            r#"[{"number":1,"title":"Bug","updated_at":"2024-01-01T00:00:00Z"}]"#,
+        ),
+        mock_json(
+            &mut server,
+            "/repos/octocat/hello/issues/comments",
+            Matcher::Any,
+            r#"[{"id":10,"body":"Me too"}]"#,
+        ),
+        server
+            .mock("GET", "/repos/octocat/hello/pulls")
+            .match_query(Matcher::UrlEncoded("direction".into(), "desc".into()))
+            .with_status(200)
+            .with_header("link", &format!("<{pulls_page_2}>; rel=\"next\""))
+            .with_body(r#"[{"number":2,"title":"Fix","updated_at":"2024-01-02T00:00:00Z"}]"#)
+            .create(),
+        mock_json(
+            &mut server,
+            "/repos/octocat/hello/pulls",
+            Matcher::UrlEncoded("page".into(), "2".into()),
+            old_pull,
+        ),
+        mock_json(
+            &mut server,
+            "/repos/octocat/hello/pulls/comments",
+            Matcher::Any,
+            "[]",
+        ),
+        mock_json(
+            &mut server,
+            "/repos/octocat/hello/issues/1/timeline",
+            Matcher::Any,
+            r#"[{"event":"labeled"}]"#,
+        ),
+        mock_json(
+            &mut server,
+            "/repos/octocat/hello/issues/2/timeline",
+            Matcher::Any,
+            r#"[{"event":"reviewed"}]"#,
+        ),
+        mock_json(
+            &mut server,
+            "/repos/octocat/hello/issues/3/timeline",
+            Matcher::Any,
+            r#"[{"event":"closed"}]"#,
+        ),
+    ];
+
+    let output = export_command(&server, output_dir.path())
+        .args(["--with-issues", "--no-cache"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let issues_dir = output_dir.path().join("octocat/hello/.gh-export/issues");
+    for file in [
+        "issues/1.json",
+        "comments/10.json",
+        "pulls/2.json",
+        "pulls/3.json",
+        "timelines/1.json",
+        "timelines/2.json",
+        "timelines/3.json",
+        "sync.json",
+    ] {
+        assert!(issues_dir.join(file).exists(), "{file} missing");
+    }
+    for mock in &first_run {
+        mock.assert();
+    }
+    for mock in first_run {
+        mock.remove();
+    }
+
+    // The second run asks for changes since the first one and stops paging
+    // pull requests at the first one it has already seen.
+    let second_run = [
+        mock_json(
+            &mut server,
+            "/repos/octocat/hello/issues",
+            Matcher::Regex("since=".into()),
+            r#"[{"number":1,"title":"Bug (renamed)","updated_at":"2030-01-01T00:00:00Z"}]"#,
+        ),
+        mock_json(
+            &mut server,
+            "/repos/octocat/hello/issues/comments",
+            Matcher::Regex("since=".into()),
+            "[]",
+        ),
+        server
+            .mock("GET", "/repos/octocat/hello/pulls")
+            .match_query(Matcher::UrlEncoded("direction".into(), "desc".into()))
+            .with_status(200)
+            .with_header("link", &format!("<{pulls_page_2}>; rel=\"next\""))
+            .with_body(old_pull)
+            .create(),
+        mock_json(
+            &mut server,
+            "/repos/octocat/hello/pulls/comments",
+            Matcher::Regex("since=".into()),
+            "[]",
+        ),
+        mock_json(
+            &mut server,
+            "/repos/octocat/hello/issues/1/timeline",
+            Matcher::Any,
+            r#"[{"event":"labeled"},{"event":"renamed"}]"#,
+        ),
+    ];
+    let skipped = [
+        mock_json(
+            &mut server,
+            "/repos/octocat/hello/pulls",
+            Matcher::UrlEncoded("page".into(), "2".into()),
+            old_pull,
+        )
+        .expect(0),
+        mock_json(
+            &mut server,
+            "/repos/octocat/hello/issues/3/timeline",
+            Matcher::Any,
+            "[]",
+        )
+        .expect(0),
+    ];
+
+    let output = export_command(&server, output_dir.path())
+        .args(["--with-issues", "--no-cache"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    for mock in second_run.iter().chain(&skipped) {
+        mock.assert();
+    }
+    let issue: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(issues_dir.join("issues/1.json")).unwrap()).unwrap();
+    assert_eq!(issue["title"], "Bug (renamed)");
+    let timeline: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(issues_dir.join("timelines/1.json")).unwrap())
+            .unwrap();
+    assert_eq!(timeline.as_array().unwrap().len(), 2);
+}
+
+#[test]
+fn test_release_assets_are_verified_before_being_skipped() {
+    use mockito::Matcher;
+
+    let (_source_dir, clone_url) = create_source_repository();
+    let repo = repository_with_clone_url(1, "octocat", "hello", clone_url.as_str());
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(&mut server, &[repo]);
+    let hello_sha256 = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+    let url = server.url();
+    let _releases = mock_json(
+        &mut server,
+        "/repos/octocat/hello/releases",
+        Matcher::Any,
+        &format!(
+            r#"[{{"id":1,"tag_name":"v1/x","assets":[
+                  {{"id":5,"name":"a.bin","size":5,"url":"{url}/assets/5","digest":"sha256:{hello_sha256}"}},
+                  {{"id":6,"name":"b.bin","size":5,"url":"{url}/assets/6","digest":null}}]}},
+                {{"id":2,"tag_name":"v1_x","assets":[]}}]"#,
+        ),
+    );
+    let asset_a = server
+        .mock("GET", "/assets/5")
+        .with_body("hello")
+        .expect(2)
+        .create();
+    let asset_b = server
+        .mock("GET", "/assets/6")
+        .with_body("world")
+        .expect(2)
+        .create();
+    let output_dir = tempfile::tempdir().unwrap();
+    let releases_dir = output_dir.path().join("octocat/hello/.gh-export/releases");
+    let run = || {
+        let output = export_command(&server, output_dir.path())
+            .args(["--with-releases", "--no-cache"])
+            .output()
+            .expect("Failed to execute command");
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    };
+
+    run();
+    let assets_dir = releases_dir.join("v1_x-1/assets");
+    assert_eq!(
+        std::fs::read_to_string(assets_dir.join("a.bin")).unwrap(),
+        "hello"
+    );
+    assert_eq!(
+        std::fs::read_to_string(assets_dir.join("b.bin")).unwrap(),
+        "world"
+    );
+    assert!(releases_dir.join("v1_x/release.json").exists());
+
+    // Intact assets are skipped; same-sized but corrupted ones are fetched again,
+    // whether GitHub provides a digest or only the stored checksum is known.
+    run();
+    std::fs::write(assets_dir.join("a.bin"), "jello").unwrap();
+    std::fs::write(assets_dir.join("b.bin"), "wurld").unwrap();
+    run();
+
+    asset_a.assert();
+    asset_b.assert();
+    assert_eq!(
+        std::fs::read_to_string(assets_dir.join("a.bin")).unwrap(),
+        "hello"
+    );
+    assert_eq!(
+        std::fs::read_to_string(assets_dir.join("b.bin")).unwrap(),
+        "world"
+    );
+}
+
+#[test]
+fn test_sanitized_asset_names_do_not_collide() {
+    let (_source_dir, clone_url) = create_source_repository();
+    let repo = repository_with_clone_url(1, "octocat", "hello", clone_url.as_str());
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(&mut server, &[repo]);
+    let url = server.url();
+    let _releases = mock_json(
+        &mut server,
+        "/repos/octocat/hello/releases",
+        mockito::Matcher::Any,
+        &format!(
+            r#"[{{"id":1,"tag_name":"v1","assets":[
+                  {{"id":5,"name":"a b.bin","size":5,"url":"{url}/assets/5","digest":null}},
+                  {{"id":6,"name":"a_b.bin","size":5,"url":"{url}/assets/6","digest":null}}]}}]"#,
+        ),
+    );
+    let _assets = [(5, "hello"), (6, "world")].map(|(id, body)| {
+        server
+            .mock("GET", format!("/assets/{id}").as_str())
+            .with_body(body)
+            .create()
+    });
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let output = export_command(&server, output_dir.path())
+        .args(["--with-releases", "--no-cache"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let assets_dir = output_dir
+        .path()
+        .join("octocat/hello/.gh-export/releases/v1/assets");
+    assert_eq!(
+        std::fs::read_to_string(assets_dir.join("a_b.bin-5")).unwrap(),
+        "hello"
+    );
+    assert_eq!(
+        std::fs::read_to_string(assets_dir.join("a_b.bin")).unwrap(),
+        "world"
+    );
+}
+
+#[test]
+fn test_protocol_change_updates_origin_url() {
+    let (source_dir, clone_url) = create_source_repository();
+    let ssh_url = source_dir.path().to_str().unwrap().to_string();
+    let repo = repository_with_clone_url(1, "octocat", "hello", clone_url.as_str()).replace(
+        r#""ssh_url":"""#,
+        &format!(r#""ssh_url":{}"#, serde_json::to_string(&ssh_url).unwrap()),
+    );
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(&mut server, &[repo]);
+    let output_dir = tempfile::tempdir().unwrap();
+    let origin_url = || {
+        git2::Repository::open(output_dir.path().join("octocat/hello"))
+            .unwrap()
+            .find_remote("origin")
+            .unwrap()
+            .url()
+            .map(str::to_string)
+    };
+
+    let output = export_command(&server, output_dir.path())
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    assert_eq!(origin_url().as_deref(), Some(clone_url.as_str()));
+
+    let output = export_command(&server, output_dir.path())
+        .args(["--protocol", "ssh"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(origin_url(), Some(ssh_url));
+}
+
+#[test]
+fn test_mirror_fetches_all_refs_and_prunes_deleted_ones() {
+    let (source_dir, clone_url) = create_source_repository();
+    let source = git2::Repository::open_bare(source_dir.path()).unwrap();
+    let main = source.head().unwrap().peel_to_commit().unwrap();
+    source.branch("feature", &main, false).unwrap();
+    source
+        .reference("refs/pull/1/head", main.id(), false, "Pull request")
+        .unwrap();
+    let repo = repository_with_clone_url(1, "octocat", "hello", clone_url.as_str());
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(&mut server, &[repo]);
+    let output_dir = tempfile::tempdir().unwrap();
+    let mirror_path = output_dir.path().join("octocat/hello");
+
+    let output = export_command(&server, output_dir.path())
+        .arg("--mirror")
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+
+    let mirror = git2::Repository::open(&mirror_path).unwrap();
+    assert!(mirror.is_bare());
+    let config = mirror.config().unwrap();
+    assert_eq!(
+        config.get_string("remote.origin.fetch").unwrap(),
+        "+refs/*:refs/*"
+    );
+    assert!(config.get_bool("remote.origin.mirror").unwrap());
+    assert!(mirror.find_reference("refs/heads/feature").is_ok());
+    assert!(mirror.find_reference("refs/pull/1/head").is_ok());
+
+    source
+        .find_reference("refs/heads/feature")
+        .unwrap()
+        .delete()
+        .unwrap();
+    let output = export_command(&server, output_dir.path())
+        .arg("--mirror")
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+
+    let mirror = git2::Repository::open(&mirror_path).unwrap();
+    assert!(mirror.find_reference("refs/heads/feature").is_err());
+    assert!(mirror.find_reference("refs/heads/main").is_ok());
+    assert!(mirror.find_reference("refs/pull/1/head").is_ok());
+}
+
+#[test]
+fn test_mirror_follows_renamed_default_branch() {
+    let (source_dir, clone_url) = create_source_repository();
+    let output_dir = tempfile::tempdir().unwrap();
+    let mirror_path = output_dir.path().join("octocat/hello");
+
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(
+        &mut server,
+        &[repository_with_clone_url(
+            1,
+            "octocat",
+            "hello",
+            clone_url.as_str(),
+        )],
+    );
+    let output = export_command(&server, output_dir.path())
+        .arg("--mirror")
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+
+    let source = git2::Repository::open_bare(source_dir.path()).unwrap();
+    source
+        .find_branch("main", git2::BranchType::Local)
+        .unwrap()
+        .rename("trunk", false)
+        .unwrap();
+    source.set_head("refs/heads/trunk").unwrap();
+
+    let mut server = mockito::Server::new();
+    let renamed = repository_with_clone_url(1, "octocat", "hello", clone_url.as_str())
+        .replace(r#""default_branch":"main""#, r#""default_branch":"trunk""#);
+    let _mocks = mock_github(&mut server, &[renamed]);
+    let output = export_command(&server, output_dir.path())
+        .arg("--mirror")
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+
+    let mirror = git2::Repository::open(&mirror_path).unwrap();
+    assert!(mirror.find_reference("refs/heads/main").is_err());
+    assert_eq!(
+        mirror.find_reference("HEAD").unwrap().symbolic_target(),
+        Some("refs/heads/trunk")
+    );
+}
+
+fn repository_with_clone_url(id: u64, owner: &str, name: &str, clone_url: &str) -> String {
+    repository_json(id, owner, name, "main").replace(
+        r#""clone_url":"""#,
+        &format!(r#""clone_url":"{clone_url}""#),
+    )
+}
+
+// Mocks the authenticated user and an unlimited rate limit, for tests that
+// mock the repository listing themselves.
+fn mock_account(server: &mut mockito::Server) -> Vec<mockito::Mock> {
+    vec![
+        server
+            .mock("GET", "/user")
+            .with_status(200)
+            .with_body(r#"{"login":"octocat","id":1,"name":null,"public_repos":1}"#)
+            .create(),
+        server
+            .mock("GET", "/rate_limit")
+            .with_status(200)
+            .with_body(r#"{"rate":{"limit":5000,"remaining":4999,"reset":0,"used":1}}"#)
+            .create(),
+    ]
+}
+
+fn mock_github(server: &mut mockito::Server, repos: &[String]) -> Vec<mockito::Mock> {
+    let mut mocks = mock_account(server);
+    mocks.push(
+        server
+            .mock("GET", "/users/octocat/repos")
+            .match_query(mockito::Matcher::Any)
+            .with_status(200)
+            .with_body(format!("[{}]", repos.join(",")))
+            .create(),
+    );
+    mocks
+}
+
+// Runs gh-export with an empty config directory, so a developer's own
+// configuration can't change what the tests export. The directory is removed
+// when the command is dropped.
+struct GhExport {
+    command: Command,
+    _home: tempfile::TempDir,
+}
+
+impl std::ops::Deref for GhExport {
+    type Target = Command;
+
+    fn deref(&self) -> &Command {
+        &self.command
+    }
+}
+
+impl std::ops::DerefMut for GhExport {
+    fn deref_mut(&mut self) -> &mut Command {
+        &mut self.command
+    }
+}
+
+fn gh_export() -> GhExport {
+    let home = tempfile::tempdir().unwrap();
+    let mut command = Command::new("cargo");
+    command
+        .args(["run", "--"])
+        .env("HOME", home.path())
+        .env("XDG_CONFIG_HOME", home.path());
+    GhExport {
+        command,
+        _home: home,
+    }
+}
+
+fn export_command(server: &mockito::Server, output_dir: &std::path::Path) -> GhExport {
+    let mut command = gh_export();
+    command
+        .args(["--yes", "--token", "invalid", "--api-url", &server.url()])
+        .arg("--output")
+        .arg(output_dir);
+    command
+}
+
+fn export_wiki(source_dir: &std::path::Path) -> serde_json::Value {
+    let clone_url = url::Url::from_file_path(source_dir.join("hello.git")).unwrap();
+    let repo = repository_with_clone_url(1, "octocat", "hello", clone_url.as_str())
+        .replace(r#""archived":false"#, r#""archived":false,"has_wiki":true"#);
+    let mut server = mockito::Server::new();
+    let _mocks = mock_github(&mut server, &[repo]);
+    let output_dir = tempfile::tempdir().unwrap();
+
+    let output = export_command(&server, output_dir.path())
+        .args(["--with-wikis", "--output-format", "json"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .find(|event| event["event"] == "repo_finished")
+        .unwrap()
+}
+
+#[test]
+fn test_missing_wiki_is_reported_as_not_initialized() {
+    let source_dir = tempfile::tempdir().unwrap();
+    init_source_repository(&source_dir.path().join("hello.git"));
+
+    let finished = export_wiki(source_dir.path());
+
+    assert_eq!(finished["result"], "success");
+    assert_eq!(finished["wiki"], "not_initialized");
+}
+
+#[test]
+fn test_existing_wiki_is_backed_up() {
+    let source_dir = tempfile::tempdir().unwrap();
+    init_source_repository(&source_dir.path().join("hello.git"));
+    init_source_repository(&source_dir.path().join("hello.wiki.git"));
+
+    let finished = export_wiki(source_dir.path());
+
+    assert_eq!(finished["result"], "success");
+    assert_eq!(finished["wiki"], "backed_up");
+}
+
+#[test]
+fn test_dry_run_is_rejected_for_commands_that_do_not_export() {
+    let config_home = tempfile::tempdir().unwrap();
+    for args in [
+        &["config", "set", "parallel", "8"][..],
+        &["status"][..],
+        &["verify", "--offline"][..],
+    ] {
+        let output = Command::new("cargo")
+            .args(["run", "--", "--dry-run"])
+            .args(args)
+            .env("HOME", config_home.path())
+            .env("XDG_CONFIG_HOME", config_home.path())
+            .output()
+            .expect("Failed to execute command");
+
+        assert_eq!(output.status.code(), Some(78), "{args:?}");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("--dry-run only applies to export"));
+    }
+    assert!(!config_home.path().join("gh-export").exists());
+}
+
+#[test]
+fn test_config_clear_without_terminal_requires_yes() {
+    let config_home = tempfile::tempdir().unwrap();
+    let config_path = config_home.path().join("gh-export/config.toml");
+    let run = |args: &[&str]| {
+        Command::new("cargo")
+            .args(["run", "--"])
+            .args(args)
+            .env("HOME", config_home.path())
+            .env("XDG_CONFIG_HOME", config_home.path())
+            .output()
+            .expect("Failed to execute command")
+    };
+
+    assert!(run(&["config", "set", "parallel", "8"]).status.success());
+
+    let output = run(&["config", "clear", "--non-interactive"]);
+    assert_eq!(output.status.code(), Some(78));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Pass --yes"));
+    assert!(config_path.exists());
+
+    let output = run(&["config", "clear", "--yes"]);
+    assert!(output.status.success());
+    assert!(!config_path.exists());
+}